@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cpu::START_ADDRESS;
+use crate::opcodes::{self, OpcodeDef, OPCODES, PLACEHOLDERS};
+use crate::{AsmError, AsmErrorKind};
+
+/// How deep constants may refer to other constants before they are considered recursive.
+const MAX_CONSTANT_DEPTH: usize = 64;
+
+struct SourceLine {
+    file: String,
+    number: usize,
+    text: String,
+}
+
+enum Operand {
+    Register(u16),
+    Expression(String),
+}
+
+enum Statement {
+    Instruction { def: &'static OpcodeDef, operands: Vec<(&'static str, Operand)> },
+    Bytes(Vec<String>),
+    Words(Vec<String>),
+}
+
+impl Statement {
+    fn size(&self) -> usize {
+        match self {
+            Statement::Instruction { def, .. } if def.syntax.ends_with("nnnn") => 4,
+            Statement::Instruction { .. } => 2,
+            Statement::Bytes(values) => values.len(),
+            Statement::Words(values) => values.len() * 2,
+        }
+    }
+}
+
+enum Symbol {
+    Label(usize),
+    Constant(String),
+}
+
+/// Assembles `source` into a rom that is loaded at 0x200. `path` names the source in error messages and relative
+/// `include` paths are resolved against its directory.
+///
+/// Every line holds an optional `label:`, followed by an instruction in the syntax of [`crate::disassemble`], a
+/// `db`/`dw` directive with a comma separated list of values, a `NAME = value` constant or an `include "file"`.
+/// Values are numbers (`42`, `0x2A`, `0b101010`), labels and constants combined with `+` and `-`. Everything after
+/// a `;` is a comment.
+pub fn assemble(source: &str, path: &Path) -> Result<Vec<u8>, AsmError> {
+    let mut lines = Vec::new();
+    let mut includes = vec![fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())];
+    read_lines(source, path, &mut includes, &mut lines)?;
+
+    // First pass: parse every line and assign addresses to the labels
+    let mut symbols = HashMap::new();
+    let mut statements = Vec::new();
+    let mut address = START_ADDRESS;
+    for line in &lines {
+        let error = |kind| AsmError { kind, file: line.file.clone(), line: line.number };
+        let mut code = strip_comment(&line.text).trim();
+
+        if let Some((label, rest)) = code.split_once(':') {
+            if is_identifier(label.trim()) {
+                define(&mut symbols, label.trim(), Symbol::Label(address)).map_err(error)?;
+                code = rest.trim();
+            }
+        }
+        if let Some((name, value)) = code.split_once('=') {
+            define(&mut symbols, name.trim(), Symbol::Constant(value.trim().to_string())).map_err(error)?;
+            continue;
+        }
+        if code.is_empty() {
+            continue;
+        }
+
+        let statement = parse_statement(code).map_err(error)?;
+        address += statement.size();
+        statements.push((line, statement));
+    }
+
+    // Second pass: evaluate the operands and encode
+    let mut rom = Vec::new();
+    for (line, statement) in statements {
+        let error = |kind| AsmError { kind, file: line.file.clone(), line: line.number };
+        let value_of = |expression: &str, bits: u32| {
+            evaluate(&symbols, expression, 0).and_then(|value| check_range(value, bits))
+        };
+        match statement {
+            Statement::Instruction { def, operands } => {
+                let mut opcode = def.pattern;
+                let mut long_operand = None;
+                for (placeholder, operand) in operands {
+                    let value = match operand {
+                        Operand::Register(register) => register,
+                        Operand::Expression(expression) => {
+                            value_of(&expression, opcodes::field_bits(placeholder)).map_err(error)?
+                        }
+                    };
+                    if placeholder == "nnnn" {
+                        long_operand = Some(value);
+                    } else {
+                        opcode = opcodes::with_field(opcode, placeholder, value);
+                    }
+                }
+                rom.extend_from_slice(&opcode.to_be_bytes());
+                if let Some(value) = long_operand {
+                    rom.extend_from_slice(&value.to_be_bytes());
+                }
+            }
+            Statement::Bytes(values) => {
+                for value in values {
+                    rom.push(value_of(&value, 8).map_err(error)? as u8);
+                }
+            }
+            Statement::Words(values) => {
+                for value in values {
+                    rom.extend_from_slice(&value_of(&value, 16).map_err(error)?.to_be_bytes());
+                }
+            }
+        }
+    }
+    Ok(rom)
+}
+
+/// Collects the lines of `source`, replacing `include` lines with the lines of the included file.
+fn read_lines(
+    source: &str,
+    path: &Path,
+    includes: &mut Vec<PathBuf>,
+    lines: &mut Vec<SourceLine>,
+) -> Result<(), AsmError> {
+    let file = path.display().to_string();
+    for (i, text) in source.lines().enumerate() {
+        let code = strip_comment(text).trim();
+        let Some(name) = strip_keyword(code, "include") else {
+            lines.push(SourceLine { file: file.clone(), number: i + 1, text: text.to_string() });
+            continue;
+        };
+
+        let error = |kind| AsmError { kind, file: file.clone(), line: i + 1 };
+        let name = name
+            .strip_prefix('"')
+            .and_then(|name| name.strip_suffix('"'))
+            .ok_or_else(|| error(AsmErrorKind::InvalidOperands(code.to_string())))?;
+        let include = path.parent().unwrap_or(Path::new("")).join(name);
+        let source = fs::read_to_string(&include).map_err(|e| {
+            error(AsmErrorKind::Io { path: include.display().to_string(), error: e })
+        })?;
+        let canonical = fs::canonicalize(&include).unwrap_or_else(|_| include.clone());
+        if includes.contains(&canonical) {
+            return Err(error(AsmErrorKind::RecursiveInclude(include.display().to_string())));
+        }
+
+        includes.push(canonical);
+        read_lines(&source, &include, includes, lines)?;
+        includes.pop();
+    }
+    Ok(())
+}
+
+fn parse_statement(code: &str) -> Result<Statement, AsmErrorKind> {
+    let (mnemonic, rest) = code.split_once(char::is_whitespace).unwrap_or((code, ""));
+    let operands: Vec<&str> = match rest.trim() {
+        "" => Vec::new(),
+        rest => rest.split(',').map(str::trim).collect(),
+    };
+    let values = || operands.iter().map(|value| value.to_string()).collect();
+
+    if mnemonic.eq_ignore_ascii_case("db") {
+        return Ok(Statement::Bytes(values()));
+    }
+    if mnemonic.eq_ignore_ascii_case("dw") {
+        return Ok(Statement::Words(values()));
+    }
+
+    let mut known = false;
+    for def in &OPCODES {
+        let (name, template) = def.syntax.split_once(' ').unwrap_or((def.syntax, ""));
+        if !name.eq_ignore_ascii_case(mnemonic) {
+            continue;
+        }
+        known = true;
+        if let Some(operands) = match_operands(template, &operands) {
+            return Ok(Statement::Instruction { def, operands });
+        }
+    }
+    if known {
+        Err(AsmErrorKind::InvalidOperands(code.to_string()))
+    } else {
+        Err(AsmErrorKind::UnknownInstruction(mnemonic.to_string()))
+    }
+}
+
+/// Matches the operands of a line against the operand syntax of an opcode, e.g. `Vx, kk` or `I, LONG nnnn`.
+fn match_operands(template: &'static str, operands: &[&str]) -> Option<Vec<(&'static str, Operand)>> {
+    let patterns: Vec<&'static str> = match template {
+        "" => Vec::new(),
+        template => template.split(", ").collect(),
+    };
+    if patterns.len() != operands.len() {
+        return None;
+    }
+
+    let mut values = Vec::new();
+    for (pattern, operand) in patterns.into_iter().zip(operands) {
+        let words: Vec<&'static str> = pattern.split(' ').collect();
+        let operand_words: Vec<&str> = operand.splitn(words.len(), char::is_whitespace).collect();
+        if operand_words.len() != words.len() {
+            return None;
+        }
+        for (word, operand) in words.into_iter().zip(operand_words) {
+            let operand = operand.trim();
+            match word {
+                "Vx" | "Vy" => values.push((word, Operand::Register(parse_register(operand)?))),
+                _ if PLACEHOLDERS.contains(&word) => {
+                    // Keeps e.g. `LD Vx, K` and `LD I, LONG nnnn` from being read as `LD Vx, kk` and `LD I, nnn`
+                    if operand.split_whitespace().next().is_some_and(is_reserved) {
+                        return None;
+                    }
+                    values.push((word, Operand::Expression(operand.to_string())));
+                }
+                _ if word.eq_ignore_ascii_case(operand) => {}
+                _ => return None,
+            }
+        }
+    }
+    Some(values)
+}
+
+fn parse_register(operand: &str) -> Option<u16> {
+    let digit = operand.strip_prefix(['V', 'v'])?;
+    if digit.len() != 1 {
+        return None;
+    }
+    u16::from_str_radix(digit, 16).ok()
+}
+
+/// Registers and the fixed operands of the instruction syntax (`I`, `DT`, `K`, ...) can't be used as symbols.
+fn is_reserved(name: &str) -> bool {
+    parse_register(name).is_some()
+        || OPCODES.iter().any(|def| {
+            def.syntax
+                .split([' ', ','])
+                .skip(1)
+                .any(|word| !word.is_empty() && !PLACEHOLDERS.contains(&word) && word.eq_ignore_ascii_case(name))
+        })
+}
+
+fn define(symbols: &mut HashMap<String, Symbol>, name: &str, symbol: Symbol) -> Result<(), AsmErrorKind> {
+    if !is_identifier(name) {
+        return Err(AsmErrorKind::InvalidExpression(name.to_string()));
+    }
+    if is_reserved(name) {
+        return Err(AsmErrorKind::ReservedName(name.to_string()));
+    }
+    if symbols.insert(name.to_string(), symbol).is_some() {
+        return Err(AsmErrorKind::DuplicateSymbol(name.to_string()));
+    }
+    Ok(())
+}
+
+/// Evaluates a sum of numbers and symbols, e.g. `sprites + 5` or `-1`.
+fn evaluate(symbols: &HashMap<String, Symbol>, expression: &str, depth: usize) -> Result<i64, AsmErrorKind> {
+    let mut total: i64 = 0;
+    let mut sign = 1;
+    let mut rest = expression.trim();
+    if let Some(negated) = rest.strip_prefix('-') {
+        sign = -1;
+        rest = negated;
+    }
+
+    loop {
+        let end = rest.find(['+', '-']).unwrap_or(rest.len());
+        let term = rest[..end].trim();
+        let value = match term.chars().next() {
+            Some('0'..='9') => parse_number(term).ok_or(AsmErrorKind::InvalidExpression(expression.to_string()))?,
+            _ if is_identifier(term) => match symbols.get(term) {
+                Some(Symbol::Label(address)) => *address as i64,
+                Some(Symbol::Constant(_)) if depth == MAX_CONSTANT_DEPTH => {
+                    return Err(AsmErrorKind::RecursiveConstant(term.to_string()))
+                }
+                Some(Symbol::Constant(value)) => evaluate(symbols, value, depth + 1)?,
+                None => return Err(AsmErrorKind::UndefinedSymbol(term.to_string())),
+            },
+            _ => return Err(AsmErrorKind::InvalidExpression(expression.to_string())),
+        };
+        total = total.saturating_add(sign * value);
+
+        if end == rest.len() {
+            return Ok(total);
+        }
+        sign = if rest[end..].starts_with('+') { 1 } else { -1 };
+        rest = &rest[end + 1..];
+    }
+}
+
+fn parse_number(text: &str) -> Option<i64> {
+    let lower = text.to_ascii_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = lower.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()
+    } else {
+        lower.parse().ok()
+    }
+}
+
+/// Accepts unsigned and two's complement values that fit in `bits`.
+fn check_range(value: i64, bits: u32) -> Result<u16, AsmErrorKind> {
+    let min = -(1 << (bits - 1));
+    let max = (1 << bits) - 1;
+    if value < min || value > max {
+        return Err(AsmErrorKind::ValueOutOfRange { value, bits });
+    }
+    Ok((value & max) as u16)
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+fn strip_comment(line: &str) -> &str {
+    line.split(';').next().unwrap_or("")
+}
+
+/// Returns the rest of `code` if it starts with the case insensitive `keyword` followed by whitespace.
+fn strip_keyword<'a>(code: &'a str, keyword: &str) -> Option<&'a str> {
+    let (word, rest) = code.split_once(char::is_whitespace)?;
+    word.eq_ignore_ascii_case(keyword).then(|| rest.trim())
+}
@@ -1,14 +1,14 @@
-use std::{fs::File, io::Read};
-use std::cmp::min;
+use std::fmt;
+use std::fs;
+use std::mem;
+use std::path::Path;
 
-use rand::Rng;
-use sdl2::event::Event;
-use sdl2::EventPump;
-use sdl2::keyboard::Keycode;
+use crate::opcodes;
+use crate::quirks::IndexIncrement;
+use crate::{CpuError, CpuErrorKind, LoadError, Quirks, RandomSource, Trace, XorShiftRng, HIRES_VIDEO_HEIGHT, HIRES_VIDEO_WIDTH, VIDEO_HEIGHT, VIDEO_WIDTH};
 
-use crate::{VIDEO_HEIGHT, VIDEO_WIDTH};
-
-const START_ADDRESS: usize = 0x200;
+const PLANE_COUNT: u8 = 2;
+pub(crate) const START_ADDRESS: usize = 0x200;
 const FONTSET_START_ADDRESS: usize = 0x50;
 
 const FONTSET_SIZE: usize = 80;
@@ -31,28 +31,144 @@ const FONTSET: [u8; FONTSET_SIZE] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
+const BIG_FONTSET_START_ADDRESS: usize = FONTSET_START_ADDRESS + FONTSET_SIZE;
+
+const BIG_FONTSET_SIZE: usize = 160;
+const BIG_FONTSET: [u8; BIG_FONTSET_SIZE] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+pub(crate) struct KeyWait {
+    pub(crate) previous_keypad: [u8; 16],
+    pub(crate) pressed: Option<u8>,
+}
+
 pub struct Cpu {
-    registers: [u8; 16],
-    memory: [u8; 4096],
-    index: u16,
-    program_counter: u16,
-    stack: [u16; 16],
-    stack_pointer: u16,
-    delay_timer: u8,
-    sound_timer: u8,
-    keypad: [u8; 16],
-    pub video: [u32; VIDEO_WIDTH * VIDEO_HEIGHT],
+    pub(crate) registers: [u8; 16],
+    pub(crate) memory: Vec<u8>,
+    pub(crate) index: u16,
+    pub(crate) program_counter: u16,
+    pub(crate) stack: [u16; 16],
+    pub(crate) stack_pointer: u16,
+    pub(crate) delay_timer: u8,
+    pub(crate) sound_timer: u8,
+    pub(crate) keypad: [u8; 16],
+    pub(crate) video: Vec<u8>,
+    pub(crate) selected_planes: u8,
+    pub(crate) hires: bool,
+    pub(crate) rpl_flags: [u8; 16],
+    pub(crate) audio_pattern: Option<[u8; 16]>,
+    pub(crate) pitch: u8,
+    pub(crate) exited: bool,
+    pub(crate) key_wait: Option<KeyWait>,
+    pub(crate) rng: Box<dyn RandomSource>,
+    pub(crate) seed: u64,
+    pub(crate) quirks: Quirks,
+    pub(crate) vblank: bool,
+    pub(crate) trace: Option<Box<Trace>>,
+    /// Set whenever `video` changes, until `take_display_changed` is called.
+    pub(crate) display_changed: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepInfo {
+    pub program_counter: u16,
+    pub opcode: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryAccess {
+    Read,
+    Write,
+}
+
+/// Observes the memory accesses of executed instructions, e.g. Fx55 stores or sprite reads by Dxyn. Instruction
+/// fetches are not reported.
+pub trait MemoryHook {
+    fn access(&mut self, address: usize, access: MemoryAccess, value: u8);
+}
+
+/// Ignores all accesses.
+impl MemoryHook for () {
+    fn access(&mut self, _address: usize, _access: MemoryAccess, _value: u8) {}
 }
 
+/// A register that can be inspected from outside of the cpu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    V(u8),
+    I,
+    Pc,
+    Sp,
+    Dt,
+    St,
+}
+
+impl Register {
+    /// Parses `V0`-`VF`, `I`, `PC`, `SP`, `DT` or `ST`, ignoring case.
+    pub fn from_name(name: &str) -> Option<Register> {
+        let upper = name.to_ascii_uppercase();
+        match upper.as_str() {
+            "I" => Some(Register::I),
+            "PC" => Some(Register::Pc),
+            "SP" => Some(Register::Sp),
+            "DT" => Some(Register::Dt),
+            "ST" => Some(Register::St),
+            _ => {
+                let digit = upper.strip_prefix('V').filter(|digit| digit.len() == 1)?;
+                u8::from_str_radix(digit, 16).ok().map(Register::V)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Register::V(x) => write!(f, "V{:X}", x),
+            Register::I => write!(f, "I"),
+            Register::Pc => write!(f, "PC"),
+            Register::Sp => write!(f, "SP"),
+            Register::Dt => write!(f, "DT"),
+            Register::St => write!(f, "ST"),
+        }
+    }
+}
+
+#[allow(dead_code)]
 #[derive(Debug)]
-enum Operation {
+pub(crate) enum Operation {
     Cls00E0(u16),
     Ret00EE(u16),
+    Scd00Cn(u16),
+    Scu00Dn(u16),
+    Scr00FB(u16),
+    Scl00FC(u16),
+    Exit00FD(u16),
+    Low00FE(u16),
+    High00FF(u16),
     Jp1nnn(u16),
     Call2nnn(u16),
     Se3xkk(u16),
     Sne4xkk(u16),
     Se5xy0(u16),
+    Save5xy2(u16),
+    Load5xy3(u16),
     Ld6xkk(u16),
     Add7xkk(u16),
     Ld8xy0(u16),
@@ -77,28 +193,49 @@ enum Operation {
     LdFx18(u16),
     AddFx1e(u16),
     LdFx29(u16),
+    LdFx30(u16),
     LdFx33(u16),
     LdFx55(u16),
     LdFx65(u16),
+    LdFx75(u16),
+    LdFx85(u16),
+    LdF000(u16),
+    PlaneFn01(u16),
+    AudioF002(u16),
+    PitchFx3A(u16),
     Null(u16),
 }
 
+impl Default for Cpu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Cpu {
-    pub fn new(rom_path: &str) -> Self {
-        let mut file = File::open(rom_path).expect(&format!("Error opening rom file {}", rom_path));
-        let num_bytes = file.metadata().expect("Unable to get rom metadata").len();
-        let mut buffer = vec![0; num_bytes as usize];
-        file.read(&mut buffer).expect("Error reading rom file");
-        let mut memory = [0; 4096];
-        // Load rom into memory from 0x200 onward
-        for i in 0..buffer.len() {
-            memory[START_ADDRESS + i] = buffer[i];
-        }
+    pub fn new() -> Self {
+        Self::with_quirks(Quirks::default())
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        let mut cpu = Self::new();
+        cpu.set_seed(seed);
+        cpu
+    }
+
+    /// Creates a cpu for the platform described by `quirks`. A memory size outside of `Quirks::MEMORY_SIZES` is
+    /// clamped to the nearest supported size.
+    pub fn with_quirks(mut quirks: Quirks) -> Self {
+        quirks.memory_size = quirks
+            .memory_size
+            .clamp(*Quirks::MEMORY_SIZES.start(), *Quirks::MEMORY_SIZES.end());
+        let seed = rand::random();
+        let mut memory = vec![0; quirks.memory_size];
 
         // Load fontset at 0x50
-        for i in 0..FONTSET_SIZE {
-            memory[FONTSET_START_ADDRESS + i] = FONTSET[i];
-        }
+        memory[FONTSET_START_ADDRESS..FONTSET_START_ADDRESS + FONTSET_SIZE].copy_from_slice(&FONTSET);
+        // Followed by the SUPER-CHIP 8x10 fontset
+        memory[BIG_FONTSET_START_ADDRESS..BIG_FONTSET_START_ADDRESS + BIG_FONTSET_SIZE].copy_from_slice(&BIG_FONTSET);
 
         Self {
             registers: [0; 16],
@@ -110,23 +247,149 @@ impl Cpu {
             delay_timer: 0,
             sound_timer: 0,
             keypad: [0; 16],
-            video: [0; 64 * 32],
+            video: vec![0; VIDEO_WIDTH * VIDEO_HEIGHT],
+            selected_planes: 1,
+            hires: false,
+            rpl_flags: [0; 16],
+            audio_pattern: None,
+            pitch: 64,
+            exited: false,
+            key_wait: None,
+            rng: Box::new(XorShiftRng::new(seed)),
+            seed,
+            quirks,
+            vblank: false,
+            trace: None,
+            display_changed: true,
+        }
+    }
+
+    pub fn from_rom(rom: &[u8]) -> Result<Self, LoadError> {
+        let mut cpu = Self::new();
+        cpu.load_rom(rom)?;
+        Ok(cpu)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(rom_path: P) -> Result<Self, LoadError> {
+        let mut cpu = Self::new();
+        cpu.load_file(rom_path)?;
+        Ok(cpu)
+    }
+
+    pub fn load_file<P: AsRef<Path>>(&mut self, rom_path: P) -> Result<(), LoadError> {
+        let rom = fs::read(rom_path)?;
+        self.load_rom(&rom)
+    }
+
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), LoadError> {
+        let max_size = self.memory.len() - START_ADDRESS;
+        if rom.is_empty() {
+            return Err(LoadError::EmptyRom);
+        }
+        if rom.len() > max_size {
+            return Err(LoadError::RomTooLarge { size: rom.len(), max_size });
+        }
+
+        // Load rom into memory from 0x200 onward
+        self.memory[START_ADDRESS..START_ADDRESS + rom.len()].copy_from_slice(rom);
+        Ok(())
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// Restarts the random number generator from `seed`, making Cxkk reproducible.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Box::new(XorShiftRng::new(seed));
+        self.seed = seed;
+    }
+
+    /// The seed of the built-in generator, meaningless after `set_random_source`.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn set_random_source(&mut self, rng: Box<dyn RandomSource>) {
+        self.rng = rng;
+    }
+
+    /// Logs every instruction executed from now on to `trace`, or stops tracing with None.
+    pub fn set_trace(&mut self, trace: Option<Trace>) {
+        self.trace = trace.map(Box::new);
+    }
+
+    /// Detaches the trace, e.g. to `Trace::finish` it.
+    pub fn take_trace(&mut self) -> Option<Trace> {
+        self.trace.take().map(|trace| *trace)
+    }
+
+    /// Runs up to `instructions_per_frame` instructions followed by a 60 Hz timer tick. The frame ends early when
+    /// the cpu stops advancing, e.g. while waiting for a key or for the next vblank.
+    pub fn run_frame(&mut self, instructions_per_frame: usize) -> Result<(), CpuError> {
+        for _ in 0..instructions_per_frame {
+            let step = self.cycle()?;
+            if step.program_counter == self.program_counter {
+                break;
+            }
         }
+        self.tick_timers();
+        Ok(())
     }
 
-    fn execute(&mut self, op: Operation) {
+    /// Decrements the delay and sound timers, must be called at 60 Hz.
+    pub fn tick_timers(&mut self) {
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
+        }
+
+        self.vblank = true;
+    }
+
+    fn execute(&mut self, op: Operation, hook: &mut dyn MemoryHook) -> Result<(), CpuErrorKind> {
         match op {
-            Operation::Cls00E0(_) => self.video.fill_with(|| 0x0),
+            Operation::Cls00E0(_) => {
+                let planes = self.selected_planes;
+                self.video.iter_mut().for_each(|pixel| *pixel &= !planes);
+                self.display_changed = true;
+            }
             Operation::Ret00EE(_) => {
+                if self.stack_pointer == 0 {
+                    return Err(CpuErrorKind::StackUnderflow);
+                }
                 self.stack_pointer -= 1;
                 self.program_counter = self.stack[self.stack_pointer as usize];
             }
+            Operation::Scd00Cn(opcode) => {
+                let rows = (opcode & 0x000F) as isize;
+                self.scroll(0, rows);
+            }
+            Operation::Scu00Dn(opcode) => {
+                let rows = (opcode & 0x000F) as isize;
+                self.scroll(0, -rows);
+            }
+            Operation::Scr00FB(_) => self.scroll(4, 0),
+            Operation::Scl00FC(_) => self.scroll(-4, 0),
+            Operation::Exit00FD(_) => {
+                self.exited = true;
+                // Stay on the exit instruction
+                self.program_counter = self.program_counter.wrapping_sub(2);
+            }
+            Operation::Low00FE(_) => self.set_hires(false),
+            Operation::High00FF(_) => self.set_hires(true),
             Operation::Jp1nnn(opcode) => {
                 let addr = opcode & 0x0FFF;
                 self.program_counter = addr;
             }
             Operation::Call2nnn(opcode) => {
                 let addr = opcode & 0x0FFF;
+                if self.stack_pointer as usize >= self.stack.len() {
+                    return Err(CpuErrorKind::StackOverflow);
+                }
                 self.stack[self.stack_pointer as usize] = self.program_counter;
                 self.stack_pointer += 1;
                 self.program_counter = addr;
@@ -135,21 +398,35 @@ impl Cpu {
                 let vx = ((opcode & 0x0F00) >> 8) as u8;
                 let byte = (opcode & 0x00FF) as u8;
                 if self.registers[vx as usize] == byte {
-                    self.program_counter += 2;
+                    self.skip_instruction()?;
                 }
             }
             Operation::Sne4xkk(opcode) => {
                 let vx = ((opcode & 0x0F00) >> 8) as u8;
                 let byte = (opcode & 0x00FF) as u8;
                 if self.registers[vx as usize] != byte {
-                    self.program_counter += 2;
+                    self.skip_instruction()?;
                 }
             }
             Operation::Se5xy0(opcode) => {
                 let vx = ((opcode & 0x0F00) >> 8) as u8;
                 let vy = ((opcode & 0x00F0) >> 4) as u8;
                 if self.registers[vx as usize] == self.registers[vy as usize] {
-                    self.program_counter += 2;
+                    self.skip_instruction()?;
+                }
+            }
+            Operation::Save5xy2(opcode) => {
+                let vx = ((opcode & 0x0F00) >> 8) as usize;
+                let vy = ((opcode & 0x00F0) >> 4) as usize;
+                for (offset, register) in Self::register_range(vx, vy).enumerate() {
+                    self.write_memory(self.index as usize + offset, self.registers[register], hook)?;
+                }
+            }
+            Operation::Load5xy3(opcode) => {
+                let vx = ((opcode & 0x0F00) >> 8) as usize;
+                let vy = ((opcode & 0x00F0) >> 4) as usize;
+                for (offset, register) in Self::register_range(vx, vy).enumerate() {
+                    self.registers[register] = self.read_memory(self.index as usize + offset, hook)?;
                 }
             }
             Operation::Ld6xkk(opcode) => {
@@ -172,67 +449,69 @@ impl Cpu {
                 let vx = ((opcode & 0x0F00) >> 8) as u8;
                 let vy = ((opcode & 0x00F0) >> 4) as u8;
                 self.registers[vx as usize] |= self.registers[vy as usize];
+                if self.quirks.logic_resets_vf {
+                    self.registers[0xF] = 0;
+                }
             }
             Operation::And8xy2(opcode) => {
                 let vx = ((opcode & 0x0F00) >> 8) as u8;
                 let vy = ((opcode & 0x00F0) >> 4) as u8;
                 self.registers[vx as usize] &= self.registers[vy as usize];
+                if self.quirks.logic_resets_vf {
+                    self.registers[0xF] = 0;
+                }
             }
             Operation::Xor8xy3(opcode) => {
                 let vx = ((opcode & 0x0F00) >> 8) as u8;
                 let vy = ((opcode & 0x00F0) >> 4) as u8;
                 self.registers[vx as usize] ^= self.registers[vy as usize];
+                if self.quirks.logic_resets_vf {
+                    self.registers[0xF] = 0;
+                }
             }
             Operation::Add8xy4(opcode) => {
                 let vx = ((opcode & 0x0F00) >> 8) as u8;
                 let vy = ((opcode & 0x00F0) >> 4) as u8;
 
-                let sum = self.registers[vx as usize] as u16 + self.registers[vy as usize] as u16;
-                if sum > 255 {
-                    self.registers[0xF] = 1;
-                } else {
-                    self.registers[0xF] = 0;
-                }
-                self.registers[vx as usize] = (sum & 0xFF) as u8;
+                let (sum, carry) = self.registers[vx as usize].overflowing_add(self.registers[vy as usize]);
+                self.registers[vx as usize] = sum;
+                self.registers[0xF] = carry as u8;
             }
             Operation::Sub8xy5(opcode) => {
                 let vx = ((opcode & 0x0F00) >> 8) as u8;
                 let vy = ((opcode & 0x00F0) >> 4) as u8;
 
-                if self.registers[vx as usize] > self.registers[vy as usize] {
-                    self.registers[0xF] = 1;
-                } else {
-                    self.registers[0xF] = 0;
-                }
-                self.registers[vx as usize] = Self::safe_subtract(self.registers[vx as usize], self.registers[vy as usize]);
+                let (difference, borrow) = self.registers[vx as usize].overflowing_sub(self.registers[vy as usize]);
+                self.registers[vx as usize] = difference;
+                self.registers[0xF] = !borrow as u8;
             }
             Operation::Shr8xy6(opcode) => {
-                let vx = (opcode & 0x0F00) >> 8 as u8;
-                self.registers[0xF] = self.registers[vx as usize] * 0x1;
-                self.registers[vx as usize] >>= 1;
+                let vx = ((opcode & 0x0F00) >> 8) as u8;
+                let vy = ((opcode & 0x00F0) >> 4) as u8;
+                let value = self.registers[if self.quirks.shift_uses_vy { vy } else { vx } as usize];
+                self.registers[vx as usize] = value >> 1;
+                self.registers[0xF] = value & 0x1;
             }
             Operation::Subn8xy7(opcode) => {
                 let vx = ((opcode & 0x0F00) >> 8) as u8;
                 let vy = ((opcode & 0x00F0) >> 4) as u8;
 
-                if self.registers[vy as usize] > self.registers[vx as usize] {
-                    self.registers[0xF] = 1;
-                } else {
-                    self.registers[0xF] = 0;
-                }
-                self.registers[vx as usize] =
-                    self.registers[vy as usize] - self.registers[vx as usize];
+                let (difference, borrow) = self.registers[vy as usize].overflowing_sub(self.registers[vx as usize]);
+                self.registers[vx as usize] = difference;
+                self.registers[0xF] = !borrow as u8;
             }
             Operation::Shl8xyE(opcode) => {
                 let vx = ((opcode & 0x0F00) >> 8) as u8;
-                self.registers[0xF] = (self.registers[vx as usize] & 0x80) >> 7;
-                self.registers[vx as usize] <<= 1;
+                let vy = ((opcode & 0x00F0) >> 4) as u8;
+                let value = self.registers[if self.quirks.shift_uses_vy { vy } else { vx } as usize];
+                self.registers[vx as usize] = value << 1;
+                self.registers[0xF] = (value & 0x80) >> 7;
             }
             Operation::Sne9xy0(opcode) => {
                 let vx = ((opcode & 0x0F00) >> 8) as u8;
                 let vy = ((opcode & 0x00F0) >> 4) as u8;
                 if self.registers[vx as usize] != self.registers[vy as usize] {
-                    self.program_counter += 2;
+                    self.skip_instruction()?;
                 }
             }
             Operation::LdAnnn(opcode) => {
@@ -241,61 +520,99 @@ impl Cpu {
             }
             Operation::JpBnnn(opcode) => {
                 let addr = opcode & 0x0FFF;
-                self.program_counter = self.registers[0] as u16 + addr;
+                let vx = if self.quirks.jump_uses_vx { (opcode & 0x0F00) >> 8 } else { 0 };
+                self.program_counter = self.registers[vx as usize] as u16 + addr;
             }
             Operation::RndCxkk(opcode) => {
                 let vx = ((opcode & 0x0F00) >> 8) as u8;
                 let byte = (opcode & 0x00FF) as u8;
 
-                self.registers[vx as usize] = Self::get_random_number() & byte;
+                self.registers[vx as usize] = self.rng.next_byte() & byte;
             }
             Operation::DrwDxyn(opcode) => {
                 let vx = ((opcode & 0x0F00) >> 8) as u8;
                 let vy = ((opcode & 0x00F0) >> 4) as u8;
-                let height = opcode & 0x000F;
-                // println!("Opcode: {} -- vx: {}, vy: {}, height: {}", opcode, vx, vy, height);
+                let n = opcode & 0x000F;
 
-                let x_pos = self.registers[vx as usize] % VIDEO_WIDTH as u8;
-                let y_pos = self.registers[vy as usize] % VIDEO_HEIGHT as u8;
+                if self.quirks.display_wait {
+                    if !self.vblank {
+                        // Retry the draw until the next vblank
+                        self.program_counter = self.program_counter.wrapping_sub(2);
+                        return Ok(());
+                    }
+                    self.vblank = false;
+                }
+
+                let (width, height) = (self.width(), self.height());
+                // Dxy0 draws a 16x16 sprite made of two bytes per row
+                let (sprite_width, sprite_height) = if n == 0 { (16, 16) } else { (8, n as usize) };
+                let sprite_size = sprite_height * sprite_width / 8;
 
-                // println!("Drawing pixel at {},{}", x_pos, y_pos);
+                let x_pos = self.registers[vx as usize] as usize % width;
+                let y_pos = self.registers[vy as usize] as usize % height;
 
                 self.registers[0xF] = 0;
+                self.display_changed = true;
+
+                // Each selected plane consumes its own sprite, stored one after the other starting at I
+                let mut address = self.index as usize;
+                for plane in 0..PLANE_COUNT {
+                    let plane_bit = 1 << plane;
+                    if self.selected_planes & plane_bit == 0 {
+                        continue;
+                    }
 
-                for row in 0..height {
-                    let sprite_byte = self.memory[(self.index + row) as usize];
-                    for col in 0..8 {
-                        let sprite_pixel = sprite_byte & (0x80 >> col);
-
-                        // println!("yPos + row: {}", (y_pos as u16 + row) as usize);
-                        // println!("xPos + col: {}", (x_pos as u16 + col) as usize);
-                        // println!("(y_pos as u16 + row) as usize * VIDEO_WIDTH: {}", (y_pos as u16 + row) as usize * VIDEO_WIDTH);
-
-                        let idx: usize = min((y_pos as u16 + row) as usize * VIDEO_WIDTH
-                            + (x_pos as u16 + col) as usize, 2047);
-                        // println!("Index: {}", idx);
-                        let screen_pixel = self.video[idx];
-                        if sprite_pixel != 0 {
-                            if screen_pixel == 0xFFFFFFFF {
-                                self.registers[0xF] = 1;
+                    for row in 0..sprite_height {
+                        let sprite_row = if sprite_width == 16 {
+                            let row_address = address + row * 2;
+                            (self.read_memory(row_address, hook)? as u16) << 8
+                                | self.read_memory(row_address + 1, hook)? as u16
+                        } else {
+                            (self.read_memory(address + row, hook)? as u16) << 8
+                        };
+                        let mut y = y_pos + row;
+                        if y >= height {
+                            if !self.quirks.sprite_wrap {
+                                break;
+                            }
+                            y %= height;
+                        }
+                        for col in 0..sprite_width {
+                            let sprite_pixel = sprite_row & (0x8000 >> col);
+
+                            let mut x = x_pos + col;
+                            if x >= width {
+                                if !self.quirks.sprite_wrap {
+                                    break;
+                                }
+                                x %= width;
+                            }
+
+                            let idx = y * width + x;
+                            let screen_pixel = self.video[idx];
+                            if sprite_pixel != 0 {
+                                if screen_pixel & plane_bit != 0 {
+                                    self.registers[0xF] = 1;
+                                }
+                                self.video[idx] ^= plane_bit;
                             }
-                            self.video[idx] ^= 0xFFFFFFFF;
                         }
                     }
+                    address += sprite_size;
                 }
             }
             Operation::SkpEx9e(opcode) => {
                 let vx = ((opcode & 0x0F00) >> 8) as u8;
                 let key = self.registers[vx as usize];
-                if self.keypad[key as usize] == 1 {
-                    self.program_counter += 2;
+                if self.key(key)? == 1 {
+                    self.skip_instruction()?;
                 }
             }
             Operation::SknpExA1(opcode) => {
                 let vx = ((opcode & 0x0F00) >> 8) as u8;
                 let key = self.registers[vx as usize];
-                if self.keypad[key as usize] != 1 {
-                    self.program_counter += 2;
+                if self.key(key)? != 1 {
+                    self.skip_instruction()?;
                 }
             }
             Operation::LdFx07(opcode) => {
@@ -306,41 +623,27 @@ impl Cpu {
             Operation::LdFx0a(opcode) => {
                 let vx = ((opcode & 0x0F00) >> 8) as u8;
 
-                if self.keypad[0] == 1 {
-                    self.registers[vx as usize] = 0;
-                } else if self.keypad[1] == 1 {
-                    self.registers[vx as usize] = 1;
-                } else if self.keypad[2] == 1 {
-                    self.registers[vx as usize] = 2;
-                } else if self.keypad[3] == 1 {
-                    self.registers[vx as usize] = 3;
-                } else if self.keypad[4] == 1 {
-                    self.registers[vx as usize] = 4;
-                } else if self.keypad[5] == 1 {
-                    self.registers[vx as usize] = 5;
-                } else if self.keypad[6] == 1 {
-                    self.registers[vx as usize] = 6;
-                } else if self.keypad[7] == 1 {
-                    self.registers[vx as usize] = 7;
-                } else if self.keypad[8] == 1 {
-                    self.registers[vx as usize] = 8;
-                } else if self.keypad[9] == 1 {
-                    self.registers[vx as usize] = 9;
-                } else if self.keypad[10] == 1 {
-                    self.registers[vx as usize] = 10;
-                } else if self.keypad[11] == 1 {
-                    self.registers[vx as usize] = 11;
-                } else if self.keypad[12] == 1 {
-                    self.registers[vx as usize] = 12;
-                } else if self.keypad[13] == 1 {
-                    self.registers[vx as usize] = 13;
-                } else if self.keypad[14] == 1 {
-                    self.registers[vx as usize] = 14;
-                } else if self.keypad[15] == 1 {
-                    self.registers[vx as usize] = 15;
-                } else {
-                    self.program_counter -= 2;
+                // Like the COSMAC VIP, the key is only accepted once it has been pressed and released again, keys that
+                // were already held when the wait started are ignored
+                let mut wait = self.key_wait.take().unwrap_or(KeyWait {
+                    previous_keypad: self.keypad,
+                    pressed: None,
+                });
+                match wait.pressed {
+                    None => {
+                        wait.pressed = (0..self.keypad.len())
+                            .find(|&key| self.keypad[key] == 1 && wait.previous_keypad[key] == 0)
+                            .map(|key| key as u8);
+                    }
+                    Some(key) if self.keypad[key as usize] == 0 => {
+                        self.registers[vx as usize] = key;
+                        return Ok(());
+                    }
+                    Some(_) => {}
                 }
+                wait.previous_keypad = self.keypad;
+                self.key_wait = Some(wait);
+                self.program_counter = self.program_counter.wrapping_sub(2);
             }
             Operation::LdFx15(opcode) => {
                 let vx = ((opcode & 0x0F00) >> 8) as u8;
@@ -352,191 +655,303 @@ impl Cpu {
             }
             Operation::AddFx1e(opcode) => {
                 let vx = ((opcode & 0x0F00) >> 8) as u8;
-                self.index += self.registers[vx as usize] as u16;
+                self.index = self.index.wrapping_add(self.registers[vx as usize] as u16);
             }
             Operation::LdFx29(opcode) => {
                 let vx = ((opcode & 0x0F00) >> 8) as u8;
-                let digit = self.registers[vx as usize];
+                let digit = self.registers[vx as usize] & 0x0F;
                 self.index = FONTSET_START_ADDRESS as u16 + (5 * digit) as u16;
             }
+            Operation::LdFx30(opcode) => {
+                let vx = ((opcode & 0x0F00) >> 8) as u8;
+                let digit = self.registers[vx as usize] & 0x0F;
+                self.index = BIG_FONTSET_START_ADDRESS as u16 + (10 * digit) as u16;
+            }
             Operation::LdFx33(opcode) => {
                 let vx = ((opcode & 0x0F00) >> 8) as u8;
                 let mut value = self.registers[vx as usize];
 
-                self.memory[(self.index + 2) as usize] = value % 10;
+                self.write_memory(self.index as usize + 2, value % 10, hook)?;
                 value /= 10;
 
-                self.memory[(self.index + 1) as usize] = value % 10;
+                self.write_memory(self.index as usize + 1, value % 10, hook)?;
                 value /= 10;
 
-                self.memory[(self.index) as usize] = value % 10;
+                self.write_memory(self.index as usize, value % 10, hook)?;
             }
             Operation::LdFx55(opcode) => {
                 let vx = ((opcode & 0x0F00) >> 8) as u8;
 
-                for i in 0..vx {
-                    let idx = self.index + i as u16;
-                    self.memory[idx as usize] = self.registers[i as usize];
+                for i in 0..=vx {
+                    let idx = self.index as usize + i as usize;
+                    self.write_memory(idx, self.registers[i as usize], hook)?;
                 }
+                self.increment_index(vx);
             }
             Operation::LdFx65(opcode) => {
                 let vx = ((opcode & 0x0F00) >> 8) as u8;
 
-                for i in 0..vx {
-                    let idx = self.index + i as u16;
-                    self.registers[i as usize] = self.memory[idx as usize];
+                for i in 0..=vx {
+                    let idx = self.index as usize + i as usize;
+                    self.registers[i as usize] = self.read_memory(idx, hook)?;
+                }
+                self.increment_index(vx);
+            }
+            Operation::LdFx75(opcode) => {
+                let vx = ((opcode & 0x0F00) >> 8) as usize;
+                self.rpl_flags[..=vx].copy_from_slice(&self.registers[..=vx]);
+            }
+            Operation::LdFx85(opcode) => {
+                let vx = ((opcode & 0x0F00) >> 8) as usize;
+                self.registers[..=vx].copy_from_slice(&self.rpl_flags[..=vx]);
+            }
+            Operation::LdF000(_) => {
+                self.index = self.fetch()?;
+                self.program_counter = self.program_counter.wrapping_add(2);
+            }
+            Operation::PlaneFn01(opcode) => {
+                let planes = ((opcode & 0x0F00) >> 8) as u8;
+                self.selected_planes = planes & 0x03;
+            }
+            Operation::AudioF002(_) => {
+                let mut pattern = [0; 16];
+                for (i, byte) in pattern.iter_mut().enumerate() {
+                    *byte = self.read_memory(self.index as usize + i, hook)?;
                 }
+                self.audio_pattern = Some(pattern);
+            }
+            Operation::PitchFx3A(opcode) => {
+                let vx = ((opcode & 0x0F00) >> 8) as u8;
+                self.pitch = self.registers[vx as usize];
             }
             Operation::Null(_) => {}
         }
+        Ok(())
     }
 
-    pub fn cycle(&mut self) {
-        let opcode = (self.memory[self.program_counter as usize] as u16) << 8
-            | self.memory[self.program_counter as usize + 1] as u16;
-
-        // println!("OpCode: {:#?}", opcode);
-
-        let nibbles = (
-            (opcode & 0xF000) >> 12 as u8,
-            (opcode & 0x0F00) >> 8 as u8,
-            (opcode & 0x00F0) >> 4 as u8,
-            (opcode & 0x000F) as u8,
-        );
-
-        let op = match nibbles {
-            (0x00, 0x00, 0x0e, 0x00) => Operation::Cls00E0(0),
-            (0x00, 0x00, 0x0e, 0x0e) => Operation::Ret00EE(0),
-            (0x01, _, _, _) => Operation::Jp1nnn(opcode),
-            (0x02, _, _, _) => Operation::Call2nnn(opcode),
-            (0x03, _, _, _) => Operation::Se3xkk(opcode),
-            (0x04, _, _, _) => Operation::Sne4xkk(opcode),
-            (0x05, _, _, 0x00) => Operation::Se5xy0(opcode),
-            (0x06, _, _, _) => Operation::Ld6xkk(opcode),
-            (0x07, _, _, _) => Operation::Add7xkk(opcode),
-            (0x08, _, _, 0x00) => Operation::Ld8xy0(opcode),
-            (0x08, _, _, 0x01) => Operation::Or8xy1(opcode),
-            (0x08, _, _, 0x02) => Operation::And8xy2(opcode),
-            (0x08, _, _, 0x03) => Operation::Xor8xy3(opcode),
-            (0x08, _, _, 0x04) => Operation::Add8xy4(opcode),
-            (0x08, _, _, 0x05) => Operation::Sub8xy5(opcode),
-            (0x08, _, _, 0x06) => Operation::Shr8xy6(opcode),
-            (0x08, _, _, 0x07) => Operation::Subn8xy7(opcode),
-            (0x08, _, _, 0x0e) => Operation::Shl8xyE(opcode),
-            (0x09, _, _, 0x00) => Operation::Sne9xy0(opcode),
-            (0x0a, _, _, _) => Operation::LdAnnn(opcode),
-            (0x0b, _, _, _) => Operation::JpBnnn(opcode),
-            (0x0c, _, _, _) => Operation::RndCxkk(opcode),
-            (0x0d, _, _, _) => Operation::DrwDxyn(opcode),
-            (0x0e, _, 0x09, 0x0e) => Operation::SkpEx9e(opcode),
-            (0x0e, _, 0x0a, 0x01) => Operation::SknpExA1(opcode),
-            (0x0f, _, 0x00, 0x07) => Operation::LdFx07(opcode),
-            (0x0f, _, 0x00, 0x0a) => Operation::LdFx0a(opcode),
-            (0x0f, _, 0x01, 0x05) => Operation::LdFx15(opcode),
-            (0x0f, _, 0x01, 0x08) => Operation::LdFx18(opcode),
-            (0x0f, _, 0x01, 0x0e) => Operation::AddFx1e(opcode),
-            (0x0f, _, 0x02, 0x09) => Operation::LdFx29(opcode),
-            (0x0f, _, 0x03, 0x03) => Operation::LdFx33(opcode),
-            (0x0f, _, 0x05, 0x05) => Operation::LdFx55(opcode),
-            (0x0f, _, 0x06, 0x05) => Operation::LdFx65(opcode),
-            _ => Operation::Null(0),
+    pub fn cycle(&mut self) -> Result<StepInfo, CpuError> {
+        self.cycle_with_hook(&mut ())
+    }
+
+    /// Executes one instruction like `cycle`, reporting its memory accesses to `hook`.
+    pub fn cycle_with_hook(&mut self, hook: &mut dyn MemoryHook) -> Result<StepInfo, CpuError> {
+        let Some(mut trace) = self.trace.take() else {
+            return self.step(hook);
         };
+        let before = Trace::snapshot(self);
+        let result = self.step(hook);
+        match &result {
+            Ok(step) => trace.record(self, before, *step),
+            Err(e) => trace.record_error(e),
+        }
+        self.trace = Some(trace);
+        result
+    }
 
-        // println!("Executing Op: {:#?}", op);
+    fn step(&mut self, hook: &mut dyn MemoryHook) -> Result<StepInfo, CpuError> {
+        let program_counter = self.program_counter;
+        let opcode = self.fetch().map_err(|kind| CpuError { kind, program_counter, opcode: 0 })?;
+        let op = opcodes::decode(opcode).map_or(Operation::Null(opcode), |def| (def.operation)(opcode));
 
-        self.program_counter += 2;
+        self.program_counter = self.program_counter.wrapping_add(2);
 
-        self.execute(op);
+        self.execute(op, hook).map_err(|kind| CpuError { kind, program_counter, opcode })?;
 
-        if self.delay_timer > 0 {
-            self.delay_timer -= 1;
-        }
+        Ok(StepInfo { program_counter, opcode })
+    }
 
-        if self.sound_timer > 0 {
-            self.sound_timer -= 1;
+    fn fetch(&self) -> Result<u16, CpuErrorKind> {
+        let pc = self.program_counter as usize;
+        Ok((self.memory_byte(pc)? as u16) << 8 | self.memory_byte(pc + 1)? as u16)
+    }
+
+    fn memory_byte(&self, address: usize) -> Result<u8, CpuErrorKind> {
+        self.memory.get(address).copied().ok_or(CpuErrorKind::MemoryOutOfBounds { address })
+    }
+
+    fn read_memory(&self, address: usize, hook: &mut dyn MemoryHook) -> Result<u8, CpuErrorKind> {
+        let value = self.memory_byte(address)?;
+        hook.access(address, MemoryAccess::Read, value);
+        Ok(value)
+    }
+
+    fn write_memory(&mut self, address: usize, value: u8, hook: &mut dyn MemoryHook) -> Result<(), CpuErrorKind> {
+        let byte = self.memory.get_mut(address).ok_or(CpuErrorKind::MemoryOutOfBounds { address })?;
+        *byte = value;
+        hook.access(address, MemoryAccess::Write, value);
+        Ok(())
+    }
+
+    fn key(&self, key: u8) -> Result<u8, CpuErrorKind> {
+        self.keypad.get(key as usize).copied().ok_or(CpuErrorKind::InvalidKey { key })
+    }
+
+    pub fn registers(&self) -> &[u8; 16] {
+        &self.registers
+    }
+
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+
+    pub fn program_counter(&self) -> u16 {
+        self.program_counter
+    }
+
+    /// The return addresses of the active subroutine calls, oldest first.
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.stack_pointer as usize]
+    }
+
+    pub fn register(&self, register: Register) -> u16 {
+        match register {
+            Register::V(x) => self.registers[x as usize & 0xF] as u16,
+            Register::I => self.index,
+            Register::Pc => self.program_counter,
+            Register::Sp => self.stack_pointer,
+            Register::Dt => self.delay_timer as u16,
+            Register::St => self.sound_timer as u16,
         }
     }
 
-    pub fn process_input(&mut self, event_pump: &mut EventPump) -> bool {
-        let mut quit = false;
-        for event in event_pump.poll_iter() {
-            match event {
-                Event::Quit { .. } => {
-                    quit = true;
-                }
-                Event::KeyDown { keycode: Some(keycode), .. } => {
-                    println!("Keycode: {}", keycode);
-                    match keycode {
-                        Keycode::Escape => quit = true,
-                        Keycode::X => self.keypad[0] = 1,
-                        Keycode::Num1 => self.keypad[1] = 1,
-                        Keycode::Num2 => self.keypad[2] = 1,
-                        Keycode::Num3 => self.keypad[3] = 1,
-                        Keycode::Q => self.keypad[4] = 1,
-                        Keycode::W => self.keypad[5] = 1,
-                        Keycode::E => self.keypad[6] = 1,
-                        Keycode::A => self.keypad[7] = 1,
-                        Keycode::S => self.keypad[8] = 1,
-                        Keycode::D => self.keypad[9] = 1,
-                        Keycode::Z => self.keypad[0xA] = 1,
-                        Keycode::C => self.keypad[0xB] = 1,
-                        Keycode::Num4 => self.keypad[0xC] = 1,
-                        Keycode::R => self.keypad[0xD] = 1,
-                        Keycode::F => self.keypad[0xE] = 1,
-                        Keycode::V => self.keypad[0xF] = 1,
-                        _ => continue,
-                    }
-                }
-                Event::KeyUp { keycode: Some(keycode), .. } => {
-                    println!("Keycode: {}", keycode);
-                    match keycode {
-                        Keycode::X => self.keypad[0] = 0,
-                        Keycode::Num1 => self.keypad[1] = 0,
-                        Keycode::Num2 => self.keypad[2] = 0,
-                        Keycode::Num3 => self.keypad[3] = 0,
-                        Keycode::Q => self.keypad[4] = 0,
-                        Keycode::W => self.keypad[5] = 0,
-                        Keycode::E => self.keypad[6] = 0,
-                        Keycode::A => self.keypad[7] = 0,
-                        Keycode::S => self.keypad[8] = 0,
-                        Keycode::D => self.keypad[9] = 0,
-                        Keycode::Z => self.keypad[0xA] = 0,
-                        Keycode::C => self.keypad[0xB] = 0,
-                        Keycode::Num4 => self.keypad[0xC] = 0,
-                        Keycode::R => self.keypad[0xD] = 0,
-                        Keycode::F => self.keypad[0xE] = 0,
-                        Keycode::V => self.keypad[0xF] = 0,
-                        _ => continue,
-                    }
-                }
-                _ => {}
-            }
+    /// Sets a register, truncating `value` to the width of the register. The stack pointer is limited to the stack
+    /// size.
+    pub fn set_register(&mut self, register: Register, value: u16) {
+        match register {
+            Register::V(x) => self.registers[x as usize & 0xF] = value as u8,
+            Register::I => self.index = value,
+            Register::Pc => self.program_counter = value,
+            Register::Sp => self.stack_pointer = value.min(self.stack.len() as u16),
+            Register::Dt => self.delay_timer = value as u8,
+            Register::St => self.sound_timer = value as u8,
         }
-        quit
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    pub fn memory_mut(&mut self) -> &mut [u8] {
+        &mut self.memory
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    /// The XO-CHIP 128 bit audio pattern, if the rom loaded one.
+    pub fn audio_pattern(&self) -> Option<&[u8; 16]> {
+        self.audio_pattern.as_ref()
+    }
+
+    /// Playback rate of the audio pattern in bits per second.
+    pub fn audio_pattern_rate(&self) -> f32 {
+        4000.0 * 2f32.powf((self.pitch as f32 - 64.0) / 48.0)
     }
 
     pub fn keypad(&self) -> &[u8; 16] {
         &self.keypad
     }
 
-    pub fn video(&self) -> Vec<u8> {
-        let mut result = Vec::new();
+    /// Presses or releases one of the 16 keys, keys above 0xF are ignored.
+    pub fn set_key(&mut self, key: u8, pressed: bool) {
+        if let Some(state) = self.keypad.get_mut(key as usize) {
+            *state = pressed as u8;
+        }
+    }
+
+    /// The display as one byte per pixel, with bit 0 set if the pixel is lit on the first plane and bit 1 for the
+    /// second XO-CHIP plane.
+    pub fn video(&self) -> &[u8] {
+        &self.video
+    }
+
+    /// Returns whether the display changed since the last call, so frontends only need to redraw when it did.
+    pub fn take_display_changed(&mut self) -> bool {
+        mem::replace(&mut self.display_changed, false)
+    }
 
-        for rgba in self.video {
-            result.push(((rgba >> 16) & 0xFF) as u8);
-            result.push(((rgba >> 8) & 0xFF) as u8);
-            result.push(((rgba) & 0xFF) as u8);
-            result.push(((rgba >> 24) & 0xFF) as u8);
+    pub fn width(&self) -> usize {
+        if self.hires {
+            HIRES_VIDEO_WIDTH
+        } else {
+            VIDEO_WIDTH
         }
+    }
 
-        result
+    pub fn height(&self) -> usize {
+        if self.hires {
+            HIRES_VIDEO_HEIGHT
+        } else {
+            VIDEO_HEIGHT
+        }
+    }
+
+    pub fn hires(&self) -> bool {
+        self.hires
+    }
+
+    /// Whether the cpu is blocked on Fx0A until a key is pressed and released. While waiting each cycle only checks the
+    /// keypad, so `run_frame` returns after a single cycle.
+    pub fn waiting_for_key(&self) -> bool {
+        self.key_wait.is_some()
+    }
+
+    /// Whether the rom executed the SUPER-CHIP exit instruction.
+    pub fn exited(&self) -> bool {
+        self.exited
+    }
+
+    fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.video = vec![0; self.width() * self.height()];
+        self.display_changed = true;
+    }
+
+    /// Moves the selected planes by `dx` columns and `dy` rows, filling the uncovered area with blank pixels.
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let (width, height) = (self.width() as isize, self.height() as isize);
+        let planes = self.selected_planes;
+        let previous = self.video.clone();
+        for y in 0..height {
+            for x in 0..width {
+                let (src_x, src_y) = (x - dx, y - dy);
+                let moved = if (0..width).contains(&src_x) && (0..height).contains(&src_y) {
+                    previous[(src_y * width + src_x) as usize]
+                } else {
+                    0
+                };
+                let idx = (y * width + x) as usize;
+                self.video[idx] = (previous[idx] & !planes) | (moved & planes);
+            }
+        }
+        self.display_changed = true;
+    }
+
+    /// Skips the next instruction, which is 4 bytes long if it is the XO-CHIP F000 nnnn long load.
+    fn skip_instruction(&mut self) -> Result<(), CpuErrorKind> {
+        let next = self.fetch()?;
+        self.program_counter = self.program_counter.wrapping_add(if next == 0xF000 { 4 } else { 2 });
+        Ok(())
     }
 
-    fn safe_subtract(lhs: u8, rhs: u8) -> u8 {
-        ((lhs as i32 - rhs as i32) & 0x00FF) as u8
+    fn register_range(vx: usize, vy: usize) -> Box<dyn Iterator<Item = usize>> {
+        if vx <= vy {
+            Box::new(vx..=vy)
+        } else {
+            Box::new((vy..=vx).rev())
+        }
     }
 
-    fn get_random_number() -> u8 {
-        rand::thread_rng().gen_range(0..=255)
+    fn increment_index(&mut self, vx: u8) {
+        self.index = match self.quirks.load_store_index {
+            IndexIncrement::Unchanged => self.index,
+            IndexIncrement::ByX => self.index.wrapping_add(vx as u16),
+            IndexIncrement::ByXPlusOne => self.index.wrapping_add(vx as u16 + 1),
+        };
     }
 }
@@ -0,0 +1,326 @@
+use crate::{Cpu, CpuError, MemoryAccess, MemoryHook, Register};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    pub const SYMBOLS: [(&'static str, Comparison); 6] = [
+        ("==", Comparison::Equal),
+        ("!=", Comparison::NotEqual),
+        ("<", Comparison::Less),
+        ("<=", Comparison::LessOrEqual),
+        (">", Comparison::Greater),
+        (">=", Comparison::GreaterOrEqual),
+    ];
+
+    pub fn from_symbol(symbol: &str) -> Option<Comparison> {
+        Self::SYMBOLS
+            .iter()
+            .find(|(name, _)| *name == symbol)
+            .map(|(_, comparison)| *comparison)
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        Self::SYMBOLS
+            .iter()
+            .find(|(_, comparison)| comparison == self)
+            .map_or("", |(name, _)| name)
+    }
+
+    fn compare(&self, lhs: u16, rhs: u16) -> bool {
+        match self {
+            Comparison::Equal => lhs == rhs,
+            Comparison::NotEqual => lhs != rhs,
+            Comparison::Less => lhs < rhs,
+            Comparison::LessOrEqual => lhs <= rhs,
+            Comparison::Greater => lhs > rhs,
+            Comparison::GreaterOrEqual => lhs >= rhs,
+        }
+    }
+}
+
+/// A comparison of a register against a constant, e.g. `V3 == 5`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Condition {
+    pub register: Register,
+    pub comparison: Comparison,
+    pub value: u16,
+}
+
+impl Condition {
+    pub fn holds(&self, cpu: &Cpu) -> bool {
+        self.comparison.compare(cpu.register(self.register), self.value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub address: u16,
+    /// The breakpoint only triggers while the condition holds.
+    pub condition: Option<Condition>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Watchpoint {
+    /// Triggers when an instruction accesses memory between `start` and `end` inclusive. `access` limits it to
+    /// reads or writes, None watches both.
+    Memory { start: usize, end: usize, access: Option<MemoryAccess> },
+    /// Triggers when an instruction changes the register, which can be any register but the PC.
+    Register(Register),
+}
+
+impl Watchpoint {
+    fn matches(&self, address: usize, access: MemoryAccess) -> bool {
+        match *self {
+            Watchpoint::Memory { start, end, access: watched } => {
+                (start..=end).contains(&address) && watched.is_none_or(|watched| watched == access)
+            }
+            Watchpoint::Register(_) => false,
+        }
+    }
+}
+
+/// Collects the first memory access of an instruction that hits a watchpoint.
+struct WatchHook<'a> {
+    watchpoints: &'a [Watchpoint],
+    hit: Option<StopReason>,
+}
+
+impl MemoryHook for WatchHook<'_> {
+    fn access(&mut self, address: usize, access: MemoryAccess, value: u8) {
+        if self.hit.is_none() && self.watchpoints.iter().any(|watchpoint| watchpoint.matches(address, access)) {
+            self.hit = Some(StopReason::MemoryWatch { address, access, value });
+        }
+    }
+}
+
+/// Why `Debugger::run_frame` paused the cpu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// A single instruction was stepped, or a subroutine call was stepped over.
+    Step,
+    Breakpoint(u16),
+    /// The last instruction accessed a watched memory address.
+    MemoryWatch { address: usize, access: MemoryAccess, value: u8 },
+    /// The last instruction changed a watched register.
+    RegisterWatch { register: Register, old: u16, new: u16 },
+    /// The subroutine that was active on `Debugger::finish` returned.
+    Return,
+    Exited,
+    Error(CpuError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Paused,
+    Running,
+    Step,
+    StepOver { return_address: u16, stack_depth: u16 },
+    Finish { stack_depth: u16 },
+}
+
+/// Runs a `Cpu` frame by frame like `Cpu::run_frame`, pausing it on breakpoints and after the requested steps.
+pub struct Debugger {
+    breakpoints: Vec<Breakpoint>,
+    watchpoints: Vec<Watchpoint>,
+    mode: Mode,
+    /// Set when execution resumes, so a breakpoint at the current PC doesn't trigger again right away.
+    resumed: bool,
+    /// Instructions executed in the current frame, which continues across pauses until the timers are ticked.
+    frame_cycles: usize,
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debugger {
+    /// Creates a debugger with the cpu paused.
+    pub fn new() -> Self {
+        Self {
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            mode: Mode::Paused,
+            resumed: false,
+            frame_cycles: 0,
+        }
+    }
+
+    /// Adds a breakpoint, replacing an existing one at the same address.
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.remove_breakpoint(breakpoint.address);
+        self.breakpoints.push(breakpoint);
+    }
+
+    /// Returns false if there was no breakpoint at `address`.
+    pub fn remove_breakpoint(&mut self, address: u16) -> bool {
+        let count = self.breakpoints.len();
+        self.breakpoints.retain(|breakpoint| breakpoint.address != address);
+        self.breakpoints.len() != count
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    /// Returns false for a watchpoint on the PC, which isn't added as the PC changes with every instruction.
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) -> bool {
+        if watchpoint == Watchpoint::Register(Register::Pc) {
+            return false;
+        }
+        if !self.watchpoints.contains(&watchpoint) {
+            self.watchpoints.push(watchpoint);
+        }
+        true
+    }
+
+    /// Removes the watchpoint at `index` in `watchpoints()`.
+    pub fn remove_watchpoint(&mut self, index: usize) -> Option<Watchpoint> {
+        (index < self.watchpoints.len()).then(|| self.watchpoints.remove(index))
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    pub fn paused(&self) -> bool {
+        self.mode == Mode::Paused
+    }
+
+    pub fn pause(&mut self) {
+        self.mode = Mode::Paused;
+    }
+
+    /// Continues execution until a breakpoint is hit.
+    pub fn resume(&mut self) {
+        self.start(Mode::Running);
+    }
+
+    /// Executes a single instruction.
+    pub fn step(&mut self) {
+        self.start(Mode::Step);
+    }
+
+    /// Executes a single instruction, running a called subroutine to completion.
+    pub fn step_over(&mut self, cpu: &Cpu) {
+        let program_counter = cpu.program_counter();
+        let is_call = cpu
+            .memory()
+            .get(program_counter as usize)
+            .is_some_and(|high_byte| high_byte & 0xF0 == 0x20);
+        if is_call {
+            self.start(Mode::StepOver {
+                return_address: program_counter.wrapping_add(2),
+                stack_depth: cpu.stack_pointer,
+            });
+        } else {
+            self.start(Mode::Step);
+        }
+    }
+
+    /// Runs until the current subroutine returns.
+    pub fn finish(&mut self, cpu: &Cpu) {
+        self.start(Mode::Finish { stack_depth: cpu.stack_pointer });
+    }
+
+    fn start(&mut self, mode: Mode) {
+        self.mode = mode;
+        self.resumed = true;
+    }
+
+    /// Runs the rest of the current frame of `instructions_per_frame` instructions and ticks the timers, unless the
+    /// cpu is paused or pauses during the frame. Returns the reason when the cpu was paused.
+    ///
+    /// Stepped instructions count towards the frame, so the timers keep running while stepping. Like
+    /// `Cpu::run_frame`, an instruction that doesn't advance the PC, e.g. a draw waiting for the vblank, ends the
+    /// frame.
+    pub fn run_frame(&mut self, cpu: &mut Cpu, instructions_per_frame: usize) -> Option<StopReason> {
+        if self.paused() {
+            return None;
+        }
+        if instructions_per_frame == 0 {
+            cpu.tick_timers();
+            return None;
+        }
+
+        loop {
+            if !self.resumed {
+                if let Some(breakpoint) = self.breakpoint_at(cpu) {
+                    return self.stop(StopReason::Breakpoint(breakpoint.address));
+                }
+            }
+            self.resumed = false;
+
+            let registers = self.watched_registers(cpu);
+            let mut hook = WatchHook { watchpoints: &self.watchpoints, hit: None };
+            let step = match cpu.cycle_with_hook(&mut hook) {
+                Ok(step) => step,
+                Err(e) => return self.stop(StopReason::Error(e)),
+            };
+            self.frame_cycles += 1;
+            let blocked = step.program_counter == cpu.program_counter;
+            let frame_ended = blocked || self.frame_cycles >= instructions_per_frame;
+            if frame_ended {
+                cpu.tick_timers();
+                self.frame_cycles = 0;
+            }
+
+            if cpu.exited() {
+                return self.stop(StopReason::Exited);
+            }
+            if let Some(hit) = hook.hit {
+                return self.stop(hit);
+            }
+            let changed = registers.into_iter().find(|&(register, old)| cpu.register(register) != old);
+            if let Some((register, old)) = changed {
+                let new = cpu.register(register);
+                return self.stop(StopReason::RegisterWatch { register, old, new });
+            }
+            match self.mode {
+                Mode::Step => return self.stop(StopReason::Step),
+                Mode::StepOver { return_address, stack_depth }
+                    if cpu.program_counter == return_address && cpu.stack_pointer == stack_depth =>
+                {
+                    return self.stop(StopReason::Step)
+                }
+                Mode::Finish { stack_depth } if cpu.stack_pointer < stack_depth => {
+                    return self.stop(StopReason::Return)
+                }
+                _ => {}
+            }
+            if frame_ended {
+                return None;
+            }
+        }
+    }
+
+    fn watched_registers(&self, cpu: &Cpu) -> Vec<(Register, u16)> {
+        self.watchpoints
+            .iter()
+            .filter_map(|watchpoint| match watchpoint {
+                Watchpoint::Register(register) => Some((*register, cpu.register(*register))),
+                Watchpoint::Memory { .. } => None,
+            })
+            .collect()
+    }
+
+    fn breakpoint_at(&self, cpu: &Cpu) -> Option<&Breakpoint> {
+        self.breakpoints.iter().find(|breakpoint| {
+            breakpoint.address == cpu.program_counter && breakpoint.condition.is_none_or(|c| c.holds(cpu))
+        })
+    }
+
+    fn stop(&mut self, reason: StopReason) -> Option<StopReason> {
+        self.mode = Mode::Paused;
+        Some(reason)
+    }
+}
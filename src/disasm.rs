@@ -0,0 +1,188 @@
+use std::fmt;
+
+use crate::cpu::START_ADDRESS;
+use crate::opcodes;
+
+/// A decoded opcode that formats as assembly, e.g. `LD V3, 0x1F` or `DRW V0, V1, 5`. Opcodes outside of the
+/// instruction set format as a `DW` data word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    opcode: u16,
+    long_operand: Option<u16>,
+    syntax: Option<&'static str>,
+}
+
+impl Instruction {
+    pub fn opcode(&self) -> u16 {
+        self.opcode
+    }
+
+    /// Returns false for opcodes that are not part of the CHIP-8, SUPER-CHIP or XO-CHIP instruction sets.
+    pub fn is_known(&self) -> bool {
+        self.syntax.is_some()
+    }
+
+    /// Size of the instruction in bytes, 4 for `F000 nnnn` and 2 for everything else.
+    pub fn size(&self) -> usize {
+        match self.syntax {
+            Some(syntax) if syntax.ends_with("nnnn") => 4,
+            _ => 2,
+        }
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        self.syntax
+            .and_then(|syntax| syntax.split(' ').next())
+            .unwrap_or("DW")
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Some(syntax) = self.syntax else {
+            return write!(f, "DW {:#06X}", self.opcode);
+        };
+
+        for (i, word) in syntax.split(' ').enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            let (word, separator) = match word.strip_suffix(',') {
+                Some(word) => (word, ","),
+                None => (word, ""),
+            };
+            match (word, opcodes::field(word, self.opcode)) {
+                ("Vx" | "Vy", Some(register)) => write!(f, "V{:X}", register)?,
+                ("kk", Some(byte)) => write!(f, "{:#04X}", byte)?,
+                ("nnn", Some(address)) => write!(f, "{:#05X}", address)?,
+                (_, Some(value)) => write!(f, "{}", value)?,
+                ("nnnn", None) => match self.long_operand {
+                    Some(address) => write!(f, "{:#06X}", address)?,
+                    None => write!(f, "?")?,
+                },
+                _ => write!(f, "{}", word)?,
+            }
+            write!(f, "{}", separator)?;
+        }
+        Ok(())
+    }
+}
+
+/// Decodes a single opcode. The operand of `F000 nnnn` is not part of the opcode, use [`disassemble_at`] to include
+/// it.
+pub fn disassemble(opcode: u16) -> Instruction {
+    Instruction {
+        opcode,
+        long_operand: None,
+        syntax: opcodes::decode(opcode).map(|def| def.syntax),
+    }
+}
+
+/// Decodes the instruction at `offset` in `bytes`, or returns None when there is no complete opcode there.
+pub fn disassemble_at(bytes: &[u8], offset: usize) -> Option<Instruction> {
+    let word = |offset: usize| Some(u16::from_be_bytes([*bytes.get(offset)?, *bytes.get(offset + 1)?]));
+    let mut instruction = disassemble(word(offset)?);
+    if instruction.size() == 4 {
+        instruction.long_operand = word(offset + 2);
+    }
+    Some(instruction)
+}
+
+/// One line of a ROM listing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListingLine {
+    Code { address: u16, instruction: Instruction },
+    Data { address: u16, bytes: Vec<u8> },
+}
+
+impl fmt::Display for ListingLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ListingLine::Code { address, instruction } => {
+                let raw = match instruction.long_operand {
+                    Some(long) => format!("{:04X} {:04X}", instruction.opcode, long),
+                    None => format!("{:04X}", instruction.opcode),
+                };
+                write!(f, "{:03X}  {:<9}  {}", address, raw, instruction)?;
+                if !instruction.is_known() {
+                    write!(f, "  ; unknown opcode")?;
+                }
+                Ok(())
+            }
+            ListingLine::Data { address, bytes } => {
+                let raw: Vec<String> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+                let values: Vec<String> = bytes.iter().map(|b| format!("{:#04X}", b)).collect();
+                write!(f, "{:03X}  {:<9}  DB {}", address, raw.concat(), values.join(", "))
+            }
+        }
+    }
+}
+
+/// Maximum number of bytes in a single data line.
+const DATA_LINE_LENGTH: usize = 4;
+
+/// Disassembles a ROM loaded at 0x200. Instructions are found by following the control flow from the entry point,
+/// everything that is never reached is listed as data.
+pub fn disassemble_rom(rom: &[u8]) -> Vec<ListingLine> {
+    let mut starts = vec![false; rom.len()];
+    let mut pending = vec![START_ADDRESS];
+
+    while let Some(address) = pending.pop() {
+        let Some(offset) = address.checked_sub(START_ADDRESS) else {
+            continue;
+        };
+        if offset >= rom.len() || starts[offset] {
+            continue;
+        }
+        let Some(instruction) = disassemble_at(rom, offset) else {
+            continue;
+        };
+        starts[offset] = true;
+
+        let opcode = instruction.opcode;
+        let next = address + instruction.size();
+        let target = (opcode & 0x0FFF) as usize;
+        match opcode & 0xF000 {
+            _ if !instruction.is_known() => {}
+            _ if opcode == 0x00EE || opcode == 0x00FD => {}
+            0x1000 => pending.push(target),
+            0x2000 => pending.extend([target, next]),
+            // The jump offset is only known at runtime, but nnn usually is the start of a jump table
+            0xB000 => pending.push(target),
+            0x3000 | 0x4000 | 0x5000 | 0x9000 | 0xE000 if is_skip(opcode) => {
+                let skipped = disassemble_at(rom, next - START_ADDRESS).map_or(2, |i| i.size());
+                pending.extend([next, next + skipped]);
+            }
+            _ => pending.push(next),
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    while offset < rom.len() {
+        let address = (START_ADDRESS + offset) as u16;
+        if starts[offset] {
+            let instruction = disassemble_at(rom, offset).expect("reachable instructions are complete");
+            lines.push(ListingLine::Code { address, instruction });
+            offset += instruction.size().min(rom.len() - offset);
+        } else {
+            let length = starts[offset..]
+                .iter()
+                .take(DATA_LINE_LENGTH)
+                .take_while(|&&start| !start)
+                .count();
+            lines.push(ListingLine::Data { address, bytes: rom[offset..offset + length].to_vec() });
+            offset += length;
+        }
+    }
+    lines
+}
+
+fn is_skip(opcode: u16) -> bool {
+    match opcode & 0xF000 {
+        0x3000 | 0x4000 => true,
+        0x5000 | 0x9000 => opcode & 0x000F == 0,
+        0xE000 => matches!(opcode & 0x00FF, 0x9E | 0xA1),
+        _ => false,
+    }
+}
@@ -0,0 +1,197 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io;
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    EmptyRom,
+    RomTooLarge { size: usize, max_size: usize },
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "Error reading rom file: {}", e),
+            LoadError::EmptyRom => write!(f, "Rom file is empty"),
+            LoadError::RomTooLarge { size, max_size } => {
+                write!(f, "Rom is {} bytes but at most {} bytes fit in memory", size, max_size)
+            }
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> Self {
+        LoadError::Io(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CpuErrorKind {
+    StackOverflow,
+    StackUnderflow,
+    MemoryOutOfBounds { address: usize },
+    InvalidKey { key: u8 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuError {
+    pub kind: CpuErrorKind,
+    pub program_counter: u16,
+    pub opcode: u16,
+}
+
+impl Display for CpuErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CpuErrorKind::StackOverflow => write!(f, "stack overflow"),
+            CpuErrorKind::StackUnderflow => write!(f, "stack underflow"),
+            CpuErrorKind::MemoryOutOfBounds { address } => {
+                write!(f, "memory access out of bounds at {:#06X}", address)
+            }
+            CpuErrorKind::InvalidKey { key } => write!(f, "invalid key {:#04X}", key),
+        }
+    }
+}
+
+impl Display for CpuError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {:#05X} (opcode {:#06X})", self.kind, self.program_counter, self.opcode)
+    }
+}
+
+impl Error for CpuError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateError {
+    InvalidMagic,
+    UnsupportedVersion(u16),
+    Truncated,
+    InvalidData(&'static str),
+    MemorySizeMismatch { expected: usize, found: usize },
+}
+
+impl Display for StateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StateError::InvalidMagic => write!(f, "Not a chip8 save state"),
+            StateError::UnsupportedVersion(version) => write!(f, "Unsupported save state version {}", version),
+            StateError::Truncated => write!(f, "Save state is truncated"),
+            StateError::InvalidData(field) => write!(f, "Save state has an invalid {}", field),
+            StateError::MemorySizeMismatch { expected, found } => write!(
+                f,
+                "Save state has {} bytes of memory but the current quirks use {} bytes",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl Error for StateError {}
+
+#[derive(Debug)]
+pub enum MovieError {
+    InvalidMagic,
+    UnsupportedVersion(u16),
+    Truncated,
+    InvalidData(&'static str),
+    RomMismatch,
+    Load(LoadError),
+    Cpu { frame: usize, error: CpuError },
+}
+
+impl Display for MovieError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MovieError::InvalidMagic => write!(f, "Not a chip8 movie"),
+            MovieError::UnsupportedVersion(version) => write!(f, "Unsupported movie version {}", version),
+            MovieError::Truncated => write!(f, "Movie is truncated"),
+            MovieError::InvalidData(field) => write!(f, "Movie has an invalid {}", field),
+            MovieError::RomMismatch => write!(f, "Movie was recorded with a different rom"),
+            MovieError::Load(e) => write!(f, "{}", e),
+            MovieError::Cpu { frame, error } => write!(f, "Frame {}: {}", frame, error),
+        }
+    }
+}
+
+impl Error for MovieError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MovieError::Load(e) => Some(e),
+            MovieError::Cpu { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<LoadError> for MovieError {
+    fn from(e: LoadError) -> Self {
+        MovieError::Load(e)
+    }
+}
+
+#[derive(Debug)]
+pub enum AsmErrorKind {
+    Io { path: String, error: io::Error },
+    UnknownInstruction(String),
+    InvalidOperands(String),
+    InvalidExpression(String),
+    UndefinedSymbol(String),
+    DuplicateSymbol(String),
+    ReservedName(String),
+    ValueOutOfRange { value: i64, bits: u32 },
+    RecursiveInclude(String),
+    RecursiveConstant(String),
+}
+
+/// Assembler error at a line of a source file.
+#[derive(Debug)]
+pub struct AsmError {
+    pub kind: AsmErrorKind,
+    pub file: String,
+    pub line: usize,
+}
+
+impl Display for AsmErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AsmErrorKind::Io { path, error } => write!(f, "error reading {}: {}", path, error),
+            AsmErrorKind::UnknownInstruction(mnemonic) => write!(f, "unknown instruction '{}'", mnemonic),
+            AsmErrorKind::InvalidOperands(line) => write!(f, "invalid operands in '{}'", line),
+            AsmErrorKind::InvalidExpression(expression) => write!(f, "invalid expression '{}'", expression),
+            AsmErrorKind::UndefinedSymbol(name) => write!(f, "undefined symbol '{}'", name),
+            AsmErrorKind::DuplicateSymbol(name) => write!(f, "symbol '{}' is already defined", name),
+            AsmErrorKind::ReservedName(name) => write!(f, "'{}' is reserved and can't be used as a symbol", name),
+            AsmErrorKind::ValueOutOfRange { value, bits } => {
+                write!(f, "value {} doesn't fit in {} bits", value, bits)
+            }
+            AsmErrorKind::RecursiveInclude(path) => write!(f, "{} includes itself", path),
+            AsmErrorKind::RecursiveConstant(name) => write!(f, "constant '{}' is defined in terms of itself", name),
+        }
+    }
+}
+
+impl Display for AsmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.kind)
+    }
+}
+
+impl Error for AsmError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            AsmErrorKind::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
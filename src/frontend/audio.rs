@@ -0,0 +1,121 @@
+use std::f32::consts::TAU;
+
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+use sdl2::AudioSubsystem;
+
+const SAMPLE_RATE: i32 = 44100;
+const PATTERN_BITS: f32 = 128.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Waveform {
+    Square,
+    Sine,
+}
+
+impl Waveform {
+    pub fn from_name(name: &str) -> Option<Waveform> {
+        match name.to_ascii_lowercase().as_str() {
+            "square" => Some(Waveform::Square),
+            "sine" => Some(Waveform::Sine),
+            _ => None,
+        }
+    }
+}
+
+pub struct Tone {
+    phase: f32,
+    phase_increment: f32,
+    volume: f32,
+    waveform: Waveform,
+    sample_rate: f32,
+    pattern: Option<[u8; 16]>,
+    pattern_position: f32,
+    pattern_increment: f32,
+}
+
+impl Tone {
+    fn square(&self, high: bool) -> f32 {
+        if high {
+            self.volume
+        } else {
+            -self.volume
+        }
+    }
+}
+
+impl AudioCallback for Tone {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            if let Some(pattern) = self.pattern {
+                // XO-CHIP roms provide their own 1 bit waveform, played back as a loop of 128 samples
+                let bit = self.pattern_position as usize;
+                *sample = self.square(pattern[bit / 8] & (0x80 >> (bit % 8)) != 0);
+                self.pattern_position = (self.pattern_position + self.pattern_increment) % PATTERN_BITS;
+                continue;
+            }
+
+            *sample = match self.waveform {
+                Waveform::Square => self.square(self.phase < 0.5),
+                Waveform::Sine => (self.phase * TAU).sin() * self.volume,
+            };
+            self.phase = (self.phase + self.phase_increment) % 1.0;
+        }
+    }
+}
+
+/// Plays a continuous tone while the sound timer is active.
+pub struct Beeper {
+    device: AudioDevice<Tone>,
+    playing: bool,
+    muted: bool,
+}
+
+impl Beeper {
+    pub fn new(audio: &AudioSubsystem, frequency: f32, volume: u8, waveform: Waveform) -> Result<Beeper, String> {
+        let desired_spec = AudioSpecDesired {
+            freq: Some(SAMPLE_RATE),
+            channels: Some(1),
+            samples: None,
+        };
+
+        let device = audio.open_playback(None, &desired_spec, |spec| Tone {
+            phase: 0.0,
+            phase_increment: frequency / spec.freq as f32,
+            volume: volume as f32 / 100.0,
+            waveform,
+            sample_rate: spec.freq as f32,
+            pattern: None,
+            pattern_position: 0.0,
+            pattern_increment: 0.0,
+        })?;
+
+        Ok(Beeper {
+            device,
+            playing: false,
+            muted: false,
+        })
+    }
+
+    pub fn update(&mut self, sound_timer: u8, pattern: Option<&[u8; 16]>, pattern_rate: f32) {
+        let play = sound_timer > 0 && !self.muted;
+        if play {
+            let mut tone = self.device.lock();
+            tone.pattern = pattern.copied();
+            tone.pattern_increment = pattern_rate / tone.sample_rate;
+        }
+        if play != self.playing {
+            if play {
+                self.device.resume();
+            } else {
+                self.device.pause();
+            }
+            self.playing = play;
+        }
+    }
+
+    pub fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+    }
+}
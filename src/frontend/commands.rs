@@ -0,0 +1,73 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chip8::LoadError;
+
+pub enum CommandError {
+    /// The arguments were invalid, the usage of the command is printed after the message.
+    Usage(String),
+    Failed(String),
+}
+
+/// Tools that are run as `chip8 <name> ...` instead of starting the emulator.
+pub struct Command {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub run: fn(&[String]) -> Result<(), CommandError>,
+}
+
+pub const COMMANDS: [Command; 2] = [
+    Command {
+        name: "disasm",
+        usage: "disasm <ROM>",
+        run: disasm,
+    },
+    Command {
+        name: "asm",
+        usage: "asm <Source> [-o <Output>]",
+        run: asm,
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|command| command.name == name)
+}
+
+/// Prints a listing of the whole rom.
+fn disasm(args: &[String]) -> Result<(), CommandError> {
+    let [rom_path] = args else {
+        return Err(CommandError::Usage("Expected <ROM>".to_string()));
+    };
+    let rom = fs::read(rom_path).map_err(|e| CommandError::Failed(format!("{}: {}", rom_path, LoadError::from(e))))?;
+    for line in chip8::disassemble_rom(&rom) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// Assembles a source file into a rom, written next to the source with a .ch8 extension unless -o is given.
+fn asm(args: &[String]) -> Result<(), CommandError> {
+    let mut source_path = None;
+    let mut output_path = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" => {
+                let path = iter.next().ok_or(CommandError::Usage("Missing value for -o".to_string()))?;
+                output_path = Some(PathBuf::from(path));
+            }
+            _ if source_path.is_none() => source_path = Some(Path::new(arg)),
+            _ => return Err(CommandError::Usage(format!("Unexpected argument '{}'", arg))),
+        }
+    }
+    let source_path = source_path.ok_or(CommandError::Usage("Expected <Source>".to_string()))?;
+    let output_path = output_path.unwrap_or_else(|| source_path.with_extension("ch8"));
+
+    let source = fs::read_to_string(source_path)
+        .map_err(|e| CommandError::Failed(format!("Error reading {}: {}", source_path.display(), e)))?;
+    let rom = chip8::assemble(&source, source_path).map_err(|e| CommandError::Failed(e.to_string()))?;
+    fs::write(&output_path, &rom)
+        .map_err(|e| CommandError::Failed(format!("Error writing {}: {}", output_path.display(), e)))?;
+    println!("Wrote {} bytes to {}", rom.len(), output_path.display());
+    Ok(())
+}
@@ -0,0 +1,180 @@
+use std::fs;
+use std::path::Path;
+
+use sdl2::keyboard::Keycode;
+
+use crate::frontend::keymap::{KeyMap, RESERVED};
+use crate::frontend::palette::{self, Palette};
+
+/// Key bindings and palettes read from a config file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    pub key_map: KeyMap,
+    /// Palettes defined in the file, switched to with F9 after the presets.
+    pub palettes: Vec<Palette>,
+    /// Palette to start with, `--palette` takes precedence over it.
+    pub palette: Option<Palette>,
+}
+
+impl Config {
+    /// Reads a config file for the rom at `rom_path`, see `parse`.
+    pub fn load(path: &Path, rom_path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Error reading {}: {}", path.display(), e))?;
+        let rom_name = Path::new(rom_path).file_name().map_or(rom_path.into(), |name| name.to_string_lossy());
+        Self::parse(&text, &rom_name).map_err(|e| format!("{}:{}", path.display(), e))
+    }
+
+    /// Parses an INI style config file:
+    /// ```text
+    /// # Comments start with # or ;
+    /// layout = azerty
+    /// 5 = Space, Up
+    /// palette.mono = #101010, #E0E0E0
+    /// palette = amber
+    ///
+    /// [pong.ch8]
+    /// 1 = Up
+    /// 4 = Down
+    /// palette = mono
+    /// ```
+    /// `layout` starts over from one of the `LAYOUTS` and `<chip-8 key> = <SDL key names>` replaces the host keys of
+    /// a chip-8 key, the `RESERVED` hotkeys are rejected. `palette.<name> = <colors>` defines a palette from 2 or 4
+    /// hex colors and `palette` selects a preset, a defined palette or colors to start with. Lines before the first
+    /// section apply to all roms, a section only applies to the rom with that file name and is applied after them.
+    pub fn parse(text: &str, rom_name: &str) -> Result<Self, String> {
+        let mut config = Config::default();
+        let mut active = true;
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            let error = |message: String| format!("{}: {}", number + 1, message);
+            if let Some(section) = line.strip_prefix('[') {
+                let section = section.strip_suffix(']').ok_or_else(|| error("Expected ']'".to_string()))?;
+                active = section.trim() == rom_name;
+                continue;
+            }
+            if !active {
+                continue;
+            }
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| error(format!("Expected <key> = <value>, found '{}'", line)))?;
+            let (name, value) = (name.trim(), value.trim());
+            if name == "layout" {
+                config.key_map =
+                    KeyMap::from_name(value).ok_or_else(|| error(format!("Unknown layout '{}'", value)))?;
+            } else if name == "palette" {
+                let defined = config.palettes.iter().find(|palette| palette.name == value).cloned();
+                config.palette = Some(defined.map_or_else(|| Palette::parse(value), Ok).map_err(error)?);
+            } else if let Some(palette_name) = name.strip_prefix("palette.") {
+                config.define_palette(palette_name, value).map_err(error)?;
+            } else {
+                let key = u8::from_str_radix(name, 16)
+                    .ok()
+                    .filter(|&key| key < 16)
+                    .ok_or_else(|| error(format!("Unknown chip-8 key '{}', expected 0-F", name)))?;
+                config.key_map.bind(key, &parse_keycodes(value).map_err(error)?);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Adds a palette, replacing an earlier definition with the same name.
+    fn define_palette(&mut self, name: &str, colors: &str) -> Result<(), String> {
+        if name.is_empty() || palette::PRESETS.iter().any(|(preset, _)| *preset == name) {
+            return Err(format!("Invalid palette name '{}'", name));
+        }
+        if Palette::from_name(colors).is_some() {
+            return Err(format!("Expected colors for palette '{}', found '{}'", name, colors));
+        }
+        let palette = Palette { name: name.to_string(), ..Palette::parse(colors)? };
+        self.palettes.retain(|defined| defined.name != name);
+        self.palettes.push(palette);
+        Ok(())
+    }
+}
+
+fn parse_keycodes(value: &str) -> Result<Vec<Keycode>, String> {
+    value
+        .split(',')
+        .map(|name| {
+            let name = name.trim();
+            let keycode = Keycode::from_name(name).ok_or_else(|| format!("Unknown key '{}'", name))?;
+            if RESERVED.contains(&keycode) {
+                return Err(format!("{} is reserved for a hotkey", name));
+            }
+            Ok(keycode)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binding_a_key_of_the_layout_moves_it() {
+        // W is 5 on qwerty, the override has to win regardless of which chip-8 key is lower
+        let key_map = Config::parse("6 = W", "game.ch8").unwrap().key_map;
+        assert_eq!(key_map.key(Keycode::W), Some(6));
+        assert_eq!(key_map.key(Keycode::E), None);
+
+        let key_map = Config::parse("4 = W", "game.ch8").unwrap().key_map;
+        assert_eq!(key_map.key(Keycode::W), Some(4));
+        assert_eq!(key_map.key(Keycode::Q), None);
+    }
+
+    #[test]
+    fn several_host_keys_can_be_bound() {
+        let key_map = Config::parse("layout = numpad\n5 = Space, Up", "game.ch8").unwrap().key_map;
+        assert_eq!(key_map.key(Keycode::Space), Some(5));
+        assert_eq!(key_map.key(Keycode::Up), Some(5));
+        assert_eq!(key_map.key(Keycode::Kp5), None);
+        assert_eq!(key_map.key(Keycode::Kp6), Some(6));
+    }
+
+    #[test]
+    fn rom_sections_only_apply_to_their_rom() {
+        let text = "layout = azerty\n\n[pong.ch8]\n1 = Up\n4 = Down\npalette = green\n\n[tetris.ch8]\n1 = Left\n";
+        let pong = Config::parse(text, "pong.ch8").unwrap();
+        assert_eq!(pong.key_map.key(Keycode::Up), Some(1));
+        assert_eq!(pong.key_map.key(Keycode::Down), Some(4));
+        assert_eq!(pong.key_map.key(Keycode::Left), None);
+        assert_eq!(pong.key_map.key(Keycode::Z), Some(5));
+        assert_eq!(pong.palette, Palette::from_name("green"));
+
+        let other = Config::parse(text, "breakout.ch8").unwrap();
+        assert_eq!(other.key_map, KeyMap::from_name("azerty").unwrap());
+        assert_eq!(other.palette, None);
+    }
+
+    #[test]
+    fn palettes_can_be_defined_and_selected() {
+        let text = "palette.mono = #101010, #E0E0E0\n\
+                    palette.mono = #000000,#FFFFFF\n\
+                    palette.xo = #000000,#FF0000,#00FF00,#FFFF00\n\
+                    palette = mono";
+        let config = Config::parse(text, "game.ch8").unwrap();
+        let names: Vec<&str> = config.palettes.iter().map(|palette| palette.name.as_str()).collect();
+        assert_eq!(names, ["mono", "xo"]);
+        assert_eq!(config.palettes[1].colors, [0x000000FF, 0xFF0000FF, 0x00FF00FF, 0xFFFF00FF]);
+        assert_eq!(config.palette.as_ref(), config.palettes.first());
+
+        let config = Config::parse("palette = #000000,#33FF66", "game.ch8").unwrap();
+        assert_eq!(config.palette.unwrap().colors[..2], [0x000000FF, 0x33FF66FF]);
+    }
+
+    #[test]
+    fn errors_report_the_line() {
+        let error = |text| Config::parse(text, "game.ch8").unwrap_err();
+        assert_eq!(error("\n# comment\nG = W"), "3: Unknown chip-8 key 'G', expected 0-F");
+        assert_eq!(error("layout = qwertz"), "1: Unknown layout 'qwertz'");
+        assert_eq!(error("\n1 = F5"), "2: F5 is reserved for a hotkey");
+        assert_eq!(error("[pong.ch8"), "1: Expected ']'");
+        assert_eq!(error("palette.green = #000000,#00FF00"), "1: Invalid palette name 'green'");
+        assert_eq!(error("\npalette.mono = #12345"), "2: Invalid color '#12345', expected #RRGGBB");
+        assert_eq!(error("palette = mono"), "1: Invalid color 'mono', expected #RRGGBB");
+    }
+}
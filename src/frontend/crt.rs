@@ -0,0 +1,138 @@
+use crate::frontend::palette;
+
+/// Post-processing of the upscaled display to look like a CRT, done on the cpu so it works with the software renderer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Effects {
+    /// Darkens the bottom row of every display pixel.
+    pub scanlines: bool,
+    /// Darkens the bottom row and right column of every display pixel.
+    pub grid: bool,
+    /// Lets lit pixels glow into their neighbours.
+    pub bloom: bool,
+    /// Darkens the corners of the screen.
+    pub vignette: bool,
+}
+
+impl Default for Effects {
+    fn default() -> Self {
+        Self { scanlines: true, grid: false, bloom: true, vignette: true }
+    }
+}
+
+impl Effects {
+    pub const NAMES: [&'static str; 4] = ["scanlines", "grid", "bloom", "vignette"];
+
+    /// Parses a comma separated list of `NAMES`, or `all`.
+    pub fn parse(text: &str) -> Result<Effects, String> {
+        if text == "all" {
+            return Ok(Effects { scanlines: true, grid: true, bloom: true, vignette: true });
+        }
+        let mut effects = Effects { scanlines: false, grid: false, bloom: false, vignette: false };
+        for name in text.split(',') {
+            match name.trim() {
+                "scanlines" => effects.scanlines = true,
+                "grid" => effects.grid = true,
+                "bloom" => effects.bloom = true,
+                "vignette" => effects.vignette = true,
+                name => return Err(format!("Unknown effect '{}', expected {} or all", name, Self::NAMES.join(","))),
+            }
+        }
+        Ok(effects)
+    }
+}
+
+/// Brightness of the darkened scanline and grid rows.
+const SCANLINE_BRIGHTNESS: f32 = 0.55;
+const GRID_BRIGHTNESS: f32 = 0.7;
+/// How much of the difference to the brighter average of the surrounding pixels is added by the bloom.
+const BLOOM_STRENGTH: f32 = 0.35;
+/// Brightness lost in the corners of the screen.
+const VIGNETTE_STRENGTH: f32 = 0.45;
+
+pub struct Crt {
+    effects: Effects,
+    enabled: bool,
+    output: Vec<u32>,
+}
+
+impl Crt {
+    pub fn new(effects: Effects, enabled: bool) -> Self {
+        Self { effects, enabled, output: Vec::new() }
+    }
+
+    /// Turns the effects on or off, returns true if they are now on.
+    pub fn toggle(&mut self) -> bool {
+        self.enabled = !self.enabled;
+        self.enabled
+    }
+
+    /// Scales the `width` by `height` RGBA `pixels` up by `factor` and applies the effects. Returns the pixels to
+    /// display with their width and height, which are the unchanged input while the effects are off.
+    pub fn apply<'a>(
+        &'a mut self,
+        pixels: &'a [u32],
+        width: usize,
+        height: usize,
+        factor: usize,
+    ) -> (&'a [u32], usize, usize) {
+        if !self.enabled {
+            return (pixels, width, height);
+        }
+
+        let effects = self.effects;
+        let glow: Vec<[f32; 3]> = if effects.bloom { bloom(pixels, width, height) } else { Vec::new() };
+        let (scaled_width, scaled_height) = (width * factor, height * factor);
+        self.output.clear();
+        self.output.reserve(scaled_width * scaled_height);
+        for y in 0..scaled_height {
+            for x in 0..scaled_width {
+                let source = y / factor * width + x / factor;
+                let mut color = palette::channels(pixels[source]);
+                if effects.bloom {
+                    for (channel, glow) in color.iter_mut().zip(glow[source]) {
+                        *channel += (glow - *channel).max(0.0) * BLOOM_STRENGTH;
+                    }
+                }
+
+                let mut brightness = 1.0;
+                let last_row = factor > 1 && y % factor == factor - 1;
+                let last_column = factor > 1 && x % factor == factor - 1;
+                if effects.scanlines && last_row {
+                    brightness *= SCANLINE_BRIGHTNESS;
+                }
+                if effects.grid && (last_row || last_column) {
+                    brightness *= GRID_BRIGHTNESS;
+                }
+                if effects.vignette {
+                    // -1 to 1 from one edge of the screen to the other
+                    let dx = (x as f32 + 0.5) / scaled_width as f32 * 2.0 - 1.0;
+                    let dy = (y as f32 + 0.5) / scaled_height as f32 * 2.0 - 1.0;
+                    brightness *= 1.0 - VIGNETTE_STRENGTH * (dx * dx + dy * dy) / 2.0;
+                }
+
+                self.output.push(palette::color(color.map(|channel| channel * brightness)));
+            }
+        }
+        (&self.output, scaled_width, scaled_height)
+    }
+}
+
+/// Average color of the 8 pixels around every pixel.
+fn bloom(pixels: &[u32], width: usize, height: usize) -> Vec<[f32; 3]> {
+    let mut glow = vec![[0.0; 3]; pixels.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let sum = &mut glow[y * width + x];
+            for ny in y.saturating_sub(1)..(y + 2).min(height) {
+                for nx in x.saturating_sub(1)..(x + 2).min(width) {
+                    if (nx, ny) != (x, y) {
+                        for (sum, channel) in sum.iter_mut().zip(palette::channels(pixels[ny * width + nx])) {
+                            *sum += channel / 8.0;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    glow
+}
@@ -0,0 +1,306 @@
+use std::io::{self, BufRead};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use chip8::{Breakpoint, Comparison, Condition, Cpu, Debugger, MemoryAccess, Register, StopReason, Watchpoint};
+
+/// Number of instructions shown before and after the PC.
+const WINDOW_BEFORE: u16 = 3;
+const WINDOW_AFTER: u16 = 6;
+
+const HELP: &str = "\
+Commands:
+  s, step                         Execute one instruction
+  n, next                         Execute one instruction, stepping over subroutine calls
+  f, finish                       Run until the current subroutine returns
+  c, continue                     Run until a breakpoint is hit
+  p, pause                        Pause execution
+  b, break <addr> [if <reg> <op> <value>]
+                                  Set a breakpoint, optionally only while e.g. `V3 == 5` holds
+  d, delete <addr>                Remove a breakpoint
+  w, watch <reg>                  Pause when an instruction changes a register other than the PC
+  w, watch <addr> [end] [r|w]     Pause when an instruction reads or writes memory between addr and end
+  u, unwatch <n>                  Remove the watchpoint with the number shown by info
+  i, info                         List the breakpoints and watchpoints
+  r, regs                         Show the registers, timers and stack
+  l, list [addr]                  Disassemble around the PC or the given address
+  x <addr> [length]               Dump memory
+  q, quit                         Quit the emulator";
+
+pub enum DebugCommand {
+    Step,
+    Next,
+    Finish,
+    Continue,
+    Pause,
+    Break(Breakpoint),
+    Delete(u16),
+    Watch(Watchpoint),
+    Unwatch(usize),
+    Info,
+    Registers,
+    List(Option<u16>),
+    Examine(u16, usize),
+    Help,
+    Quit,
+}
+
+/// Reads debugger commands from stdin on a separate thread, so the emulator keeps running while waiting for input.
+pub fn spawn_repl() -> Receiver<Result<DebugCommand, String>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        println!("Debugger started, the cpu is paused. Type 'help' for a list of commands.");
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if line.trim().is_empty() {
+                continue;
+            }
+            if sender.send(parse(&line)).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+fn parse(line: &str) -> Result<DebugCommand, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let address = |i: usize| {
+        let word = words.get(i).ok_or("Expected an address")?;
+        parse_number(word).ok_or(format!("Invalid address '{}'", word))
+    };
+    match words[0] {
+        "s" | "step" => Ok(DebugCommand::Step),
+        "n" | "next" => Ok(DebugCommand::Next),
+        "f" | "finish" => Ok(DebugCommand::Finish),
+        "c" | "continue" => Ok(DebugCommand::Continue),
+        "p" | "pause" => Ok(DebugCommand::Pause),
+        "b" | "break" => {
+            let condition = match words.get(2..) {
+                Some([]) | None => None,
+                Some(["if", register, comparison, value]) => Some(Condition {
+                    register: Register::from_name(register).ok_or(format!("Unknown register '{}'", register))?,
+                    comparison: Comparison::from_symbol(comparison)
+                        .ok_or(format!("Unknown comparison '{}'", comparison))?,
+                    value: parse_number(value).ok_or(format!("Invalid value '{}'", value))?,
+                }),
+                Some(_) => return Err("Expected break <addr> [if <reg> <op> <value>]".to_string()),
+            };
+            Ok(DebugCommand::Break(Breakpoint { address: address(1)?, condition }))
+        }
+        "d" | "delete" => Ok(DebugCommand::Delete(address(1)?)),
+        "w" | "watch" => {
+            if let Some(register) = words.get(1).and_then(|name| Register::from_name(name)) {
+                if register == Register::Pc {
+                    return Err("The PC changes with every instruction, use a breakpoint instead".to_string());
+                }
+                return match words.len() {
+                    2 => Ok(DebugCommand::Watch(Watchpoint::Register(register))),
+                    _ => Err("Expected watch <reg>".to_string()),
+                };
+            }
+            let start = address(1)? as usize;
+            let mut rest = &words[2..];
+            let end = match rest.first().and_then(|word| parse_number(word)) {
+                Some(end) => {
+                    rest = &rest[1..];
+                    end as usize
+                }
+                None => start,
+            };
+            let access = match rest {
+                [] => None,
+                ["r"] => Some(MemoryAccess::Read),
+                ["w"] => Some(MemoryAccess::Write),
+                _ => return Err("Expected watch <addr> [end] [r|w]".to_string()),
+            };
+            Ok(DebugCommand::Watch(Watchpoint::Memory { start, end: end.max(start), access }))
+        }
+        "u" | "unwatch" => {
+            let word = words.get(1).ok_or("Expected a watchpoint number")?;
+            Ok(DebugCommand::Unwatch(word.parse().map_err(|_| format!("Invalid watchpoint '{}'", word))?))
+        }
+        "i" | "info" => Ok(DebugCommand::Info),
+        "r" | "regs" => Ok(DebugCommand::Registers),
+        "l" | "list" => Ok(DebugCommand::List(if words.len() > 1 { Some(address(1)?) } else { None })),
+        "x" => {
+            let length = match words.get(2) {
+                Some(word) => parse_number(word).ok_or(format!("Invalid length '{}'", word))? as usize,
+                None => 16,
+            };
+            Ok(DebugCommand::Examine(address(1)?, length))
+        }
+        "h" | "help" => Ok(DebugCommand::Help),
+        "q" | "quit" => Ok(DebugCommand::Quit),
+        command => Err(format!("Unknown command '{}', type 'help' for a list of commands", command)),
+    }
+}
+
+/// Accepts hexadecimal numbers with a 0x prefix and decimal numbers.
+fn parse_number(text: &str) -> Option<u16> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+/// Applies a command, returns false if the emulator should quit.
+pub fn execute(command: DebugCommand, debugger: &mut Debugger, cpu: &Cpu) -> bool {
+    match command {
+        DebugCommand::Step => debugger.step(),
+        DebugCommand::Next => debugger.step_over(cpu),
+        DebugCommand::Finish => {
+            if cpu.stack().is_empty() {
+                println!("Not in a subroutine, running until a breakpoint is hit");
+            }
+            debugger.finish(cpu);
+        }
+        DebugCommand::Continue => debugger.resume(),
+        DebugCommand::Pause => {
+            debugger.pause();
+            print_state(cpu, debugger);
+        }
+        DebugCommand::Break(breakpoint) => {
+            debugger.add_breakpoint(breakpoint);
+            println!("Breakpoint set at {}", describe(&breakpoint));
+        }
+        DebugCommand::Delete(address) => {
+            if !debugger.remove_breakpoint(address) {
+                println!("No breakpoint at {:#05X}", address);
+            }
+        }
+        DebugCommand::Watch(watchpoint) => {
+            if debugger.add_watchpoint(watchpoint) {
+                println!("Watching {}", describe_watchpoint(&watchpoint));
+            }
+        }
+        DebugCommand::Unwatch(index) => {
+            if debugger.remove_watchpoint(index).is_none() {
+                println!("No watchpoint {}", index);
+            }
+        }
+        DebugCommand::Info => {
+            if debugger.breakpoints().is_empty() && debugger.watchpoints().is_empty() {
+                println!("No breakpoints or watchpoints");
+            }
+            for breakpoint in debugger.breakpoints() {
+                println!("Breakpoint {}", describe(breakpoint));
+            }
+            for (i, watchpoint) in debugger.watchpoints().iter().enumerate() {
+                println!("Watchpoint {}: {}", i, describe_watchpoint(watchpoint));
+            }
+        }
+        DebugCommand::Registers => print_registers(cpu),
+        DebugCommand::List(address) => print_window(cpu, debugger, address.unwrap_or(cpu.program_counter())),
+        DebugCommand::Examine(address, length) => print_memory(cpu, address as usize, length),
+        DebugCommand::Help => println!("{}", HELP),
+        DebugCommand::Quit => return false,
+    }
+    true
+}
+
+/// Reports why the debugger paused the cpu.
+pub fn print_stop(reason: StopReason, cpu: &Cpu, debugger: &Debugger) {
+    match reason {
+        // Errors are reported by the main loop like without the debugger
+        StopReason::Step | StopReason::Return | StopReason::Error(_) => {}
+        StopReason::Breakpoint(address) => println!("Breakpoint at {:#05X}", address),
+        StopReason::MemoryWatch { address, access, value } => {
+            let action = match access {
+                MemoryAccess::Read => "read",
+                MemoryAccess::Write => "wrote",
+            };
+            println!("Instruction {} {:#04X} at {:#05X}", action, value, address);
+        }
+        StopReason::RegisterWatch { register, old, new } => {
+            println!("{} changed from {:#X} to {:#X}", register, old, new);
+        }
+        StopReason::Exited => println!("The rom exited"),
+    }
+    print_state(cpu, debugger);
+}
+
+fn print_state(cpu: &Cpu, debugger: &Debugger) {
+    print_registers(cpu);
+    print_window(cpu, debugger, cpu.program_counter());
+}
+
+fn print_registers(cpu: &Cpu) {
+    let registers: Vec<String> = cpu
+        .registers()
+        .iter()
+        .enumerate()
+        .map(|(i, value)| format!("V{:X} {:02X}", i, value))
+        .collect();
+    println!("{}", registers[..8].join("  "));
+    println!("{}", registers[8..].join("  "));
+    println!(
+        "PC {:#05X}  I {:#05X}  DT {}  ST {}",
+        cpu.program_counter(),
+        cpu.index(),
+        cpu.delay_timer(),
+        cpu.sound_timer()
+    );
+    let stack: Vec<String> = cpu.stack().iter().map(|address| format!("{:#05X}", address)).collect();
+    println!("Stack [{}]", stack.join(", "));
+}
+
+fn print_window(cpu: &Cpu, debugger: &Debugger, center: u16) {
+    let start = center.saturating_sub(WINDOW_BEFORE * 2);
+    let mut address = start;
+    while address <= center.saturating_add(WINDOW_AFTER * 2) {
+        let Some(instruction) = chip8::disassemble_at(cpu.memory(), address as usize) else {
+            break;
+        };
+        let marker = if address == cpu.program_counter() { "=>" } else { "  " };
+        let breakpoint = if debugger.breakpoints().iter().any(|b| b.address == address) { "*" } else { " " };
+        println!("{}{} {:03X}  {:04X}  {}", marker, breakpoint, address, instruction.opcode(), instruction);
+        let Some(next) = address.checked_add(instruction.size() as u16) else {
+            break;
+        };
+        address = next;
+    }
+}
+
+fn print_memory(cpu: &Cpu, address: usize, length: usize) {
+    let memory = cpu.memory();
+    let end = address.saturating_add(length).min(memory.len());
+    for line in (address.min(end)..end).step_by(16) {
+        let bytes: Vec<String> = memory[line..(line + 16).min(end)]
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect();
+        println!("{:03X}  {}", line, bytes.join(" "));
+    }
+}
+
+fn describe(breakpoint: &Breakpoint) -> String {
+    match breakpoint.condition {
+        Some(condition) => format!(
+            "{:#05X} if {} {} {}",
+            breakpoint.address,
+            condition.register,
+            condition.comparison.symbol(),
+            condition.value
+        ),
+        None => format!("{:#05X}", breakpoint.address),
+    }
+}
+
+fn describe_watchpoint(watchpoint: &Watchpoint) -> String {
+    match *watchpoint {
+        Watchpoint::Register(register) => format!("register {}", register),
+        Watchpoint::Memory { start, end, access } => {
+            let access = match access {
+                Some(MemoryAccess::Read) => "reads of",
+                Some(MemoryAccess::Write) => "writes to",
+                None => "accesses to",
+            };
+            if start == end {
+                format!("{} {:#05X}", access, start)
+            } else {
+                format!("{} {:#05X}-{:#05X}", access, start, end)
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::VecDeque;
+
+use crate::frontend::palette;
+
+/// Reduces the flicker of sprites that are erased and redrawn every frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterMode {
+    /// Lit pixels light up immediately and fade out, keeping `decay` of their brightness every frame like a CRT
+    /// phosphor.
+    Phosphor { decay: f32 },
+    /// Pixels are lit if they were lit in any of the last `frames` frames.
+    Or { frames: usize },
+}
+
+impl Default for FilterMode {
+    fn default() -> Self {
+        FilterMode::Phosphor { decay: 0.6 }
+    }
+}
+
+impl FilterMode {
+    /// Parses `phosphor[:<decay>]` or `or[:<frames>]`.
+    pub fn parse(text: &str) -> Result<FilterMode, String> {
+        let (name, value) = match text.split_once(':') {
+            Some((name, value)) => (name, Some(value)),
+            None => (text, None),
+        };
+        match name {
+            "phosphor" => {
+                let decay = match value {
+                    Some(value) => value.parse().map_err(|_| format!("Invalid decay '{}'", value))?,
+                    None => 0.6,
+                };
+                if !(0.0..1.0).contains(&decay) {
+                    return Err("The phosphor decay must be at least 0 and less than 1".to_string());
+                }
+                Ok(FilterMode::Phosphor { decay })
+            }
+            "or" => {
+                let frames = match value {
+                    Some(value) => value.parse().map_err(|_| format!("Invalid frame count '{}'", value))?,
+                    None => 2,
+                };
+                if frames == 0 {
+                    return Err("The frame count must be at least 1".to_string());
+                }
+                Ok(FilterMode::Or { frames })
+            }
+            _ => Err(format!("Unknown filter '{}', expected phosphor[:<decay>] or or[:<frames>]", text)),
+        }
+    }
+}
+
+/// Converts the plane indices of `Cpu::video` to colors, optionally through a `FilterMode`.
+pub struct Filter {
+    mode: FilterMode,
+    enabled: bool,
+    /// Displayed color channels of every pixel for `FilterMode::Phosphor`.
+    history: Vec<[f32; 3]>,
+    /// The last frames for `FilterMode::Or`, newest first.
+    frames: VecDeque<Vec<u8>>,
+    output: Vec<u32>,
+}
+
+impl Filter {
+    pub fn new(mode: FilterMode, enabled: bool) -> Self {
+        Self {
+            mode,
+            enabled,
+            history: Vec::new(),
+            frames: VecDeque::new(),
+            output: Vec::new(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Turns the filter on or off, returns true if it is now on.
+    pub fn toggle(&mut self) -> bool {
+        self.enabled = !self.enabled;
+        self.history.clear();
+        self.frames.clear();
+        self.enabled
+    }
+
+    /// Returns the RGBA color of every pixel of the next frame.
+    pub fn apply(&mut self, video: &[u8], colors: &[u32; 4]) -> &[u32] {
+        self.output.clear();
+        if !self.enabled {
+            self.output.extend(video.iter().map(|&pixel| colors[pixel as usize]));
+            return &self.output;
+        }
+
+        match self.mode {
+            FilterMode::Phosphor { decay } => {
+                // The display switched between lores and hires
+                if self.history.len() != video.len() {
+                    self.history = video.iter().map(|&pixel| palette::channels(colors[pixel as usize])).collect();
+                }
+                for (pixel, displayed) in video.iter().zip(&mut self.history) {
+                    let target = palette::channels(colors[*pixel as usize]);
+                    for (displayed, target) in displayed.iter_mut().zip(target) {
+                        *displayed = if *pixel != 0 { target } else { target + (*displayed - target) * decay };
+                    }
+                    self.output.push(palette::color(*displayed));
+                }
+            }
+            FilterMode::Or { frames } => {
+                if self.frames.front().is_some_and(|frame| frame.len() != video.len()) {
+                    self.frames.clear();
+                }
+                self.frames.truncate(frames - 1);
+                self.frames.push_front(video.to_vec());
+                self.output.extend((0..video.len()).map(|i| {
+                    let pixel = self.frames.iter().fold(0, |pixel, frame| pixel | frame[i]);
+                    colors[pixel as usize]
+                }));
+            }
+        }
+        &self.output
+    }
+}
@@ -0,0 +1,157 @@
+use sdl2::keyboard::Keycode;
+
+/// Host keys for the chip-8 keys 0-F, placed like the COSMAC VIP hex keypad on the left side of the keyboard:
+/// ```text
+/// 1 2 3 C
+/// 4 5 6 D
+/// 7 8 9 E
+/// A 0 B F
+/// ```
+pub const LAYOUTS: [(&str, [Keycode; 16]); 4] = [
+    (
+        "qwerty",
+        [
+            Keycode::X,
+            Keycode::Num1,
+            Keycode::Num2,
+            Keycode::Num3,
+            Keycode::Q,
+            Keycode::W,
+            Keycode::E,
+            Keycode::A,
+            Keycode::S,
+            Keycode::D,
+            Keycode::Z,
+            Keycode::C,
+            Keycode::Num4,
+            Keycode::R,
+            Keycode::F,
+            Keycode::V,
+        ],
+    ),
+    (
+        "azerty",
+        [
+            Keycode::X,
+            Keycode::Num1,
+            Keycode::Num2,
+            Keycode::Num3,
+            Keycode::A,
+            Keycode::Z,
+            Keycode::E,
+            Keycode::Q,
+            Keycode::S,
+            Keycode::D,
+            Keycode::W,
+            Keycode::C,
+            Keycode::Num4,
+            Keycode::R,
+            Keycode::F,
+            Keycode::V,
+        ],
+    ),
+    (
+        "dvorak",
+        [
+            Keycode::Q,
+            Keycode::Num1,
+            Keycode::Num2,
+            Keycode::Num3,
+            Keycode::Quote,
+            Keycode::Comma,
+            Keycode::Period,
+            Keycode::A,
+            Keycode::O,
+            Keycode::E,
+            Keycode::Semicolon,
+            Keycode::J,
+            Keycode::Num4,
+            Keycode::P,
+            Keycode::U,
+            Keycode::K,
+        ],
+    ),
+    (
+        "numpad",
+        [
+            Keycode::Kp0,
+            Keycode::Kp1,
+            Keycode::Kp2,
+            Keycode::Kp3,
+            Keycode::Kp4,
+            Keycode::Kp5,
+            Keycode::Kp6,
+            Keycode::Kp7,
+            Keycode::Kp8,
+            Keycode::Kp9,
+            Keycode::KpDivide,
+            Keycode::KpMultiply,
+            Keycode::KpMinus,
+            Keycode::KpPlus,
+            Keycode::KpEnter,
+            Keycode::KpPeriod,
+        ],
+    ),
+];
+
+/// Host keys taken by the frontend's hotkeys, which can't be bound to chip-8 keys. Alt+Enter is taken too, but Enter
+/// on its own can be bound.
+pub const RESERVED: [Keycode; 15] = [
+    Keycode::Escape,
+    Keycode::M,
+    Keycode::Backspace,
+    Keycode::F1,
+    Keycode::F2,
+    Keycode::F3,
+    Keycode::F4,
+    Keycode::F5,
+    Keycode::F6,
+    Keycode::F7,
+    Keycode::F8,
+    Keycode::F9,
+    Keycode::F10,
+    Keycode::F11,
+    Keycode::F12,
+];
+
+/// Maps host keys to chip-8 keys. A chip-8 key can be bound to several host keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMap {
+    bindings: [Vec<Keycode>; 16],
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self::from_layout(LAYOUTS[0].1)
+    }
+}
+
+impl KeyMap {
+    pub fn from_layout(layout: [Keycode; 16]) -> Self {
+        Self { bindings: layout.map(|keycode| vec![keycode]) }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        LAYOUTS
+            .iter()
+            .find(|(layout, _)| *layout == name)
+            .map(|(_, layout)| Self::from_layout(*layout))
+    }
+
+    /// Replaces the host keys of the chip-8 `key`. The host keys are taken away from any other chip-8 key they were
+    /// bound to, which can leave that key unbound.
+    pub fn bind(&mut self, key: u8, keycodes: &[Keycode]) {
+        for bound in self.bindings.iter_mut() {
+            bound.retain(|keycode| !keycodes.contains(keycode));
+        }
+        self.bindings[key as usize] = keycodes.to_vec();
+    }
+
+    pub fn key(&self, keycode: Keycode) -> Option<u8> {
+        self.bindings
+            .iter()
+            .position(|keycodes| keycodes.contains(&keycode))
+            .map(|key| key as u8)
+    }
+}
+
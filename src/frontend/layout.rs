@@ -0,0 +1,41 @@
+use sdl2::rect::Rect;
+
+/// How the display is scaled to fill the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scaling {
+    /// As large as fits, which can stretch some pixels by one more screen pixel than others.
+    Fit,
+    /// Only whole multiples of the display size, so every pixel is the same size.
+    Integer,
+}
+
+impl Scaling {
+    pub const NAMES: [(&'static str, Scaling); 2] = [("fit", Scaling::Fit), ("integer", Scaling::Integer)];
+
+    pub fn from_name(name: &str) -> Option<Scaling> {
+        Self::NAMES
+            .iter()
+            .find(|(scaling, _)| *scaling == name)
+            .map(|(_, scaling)| *scaling)
+    }
+}
+
+/// Returns the area of the window the display is drawn to, centered with black bars around it to keep the aspect
+/// ratio. Integer scaling falls back to fitting the display when the window is smaller than it.
+pub fn viewport(window: (u32, u32), display: (u32, u32), scaling: Scaling) -> Rect {
+    let (window_width, window_height) = window;
+    let (display_width, display_height) = (display.0.max(1), display.1.max(1));
+    let scale = (window_width as f64 / display_width as f64).min(window_height as f64 / display_height as f64);
+    let scale = match scaling {
+        Scaling::Integer if scale >= 1.0 => scale.floor(),
+        _ => scale,
+    };
+    let width = ((display_width as f64 * scale) as u32).max(1);
+    let height = ((display_height as f64 * scale) as u32).max(1);
+    Rect::new(
+        (window_width.saturating_sub(width) / 2) as i32,
+        (window_height.saturating_sub(height) / 2) as i32,
+        width,
+        height,
+    )
+}
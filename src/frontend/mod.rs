@@ -0,0 +1,12 @@
+pub mod audio;
+pub mod commands;
+pub mod config;
+pub mod crt;
+pub mod debug;
+pub mod filter;
+pub mod keymap;
+pub mod layout;
+pub mod movies;
+pub mod options;
+pub mod palette;
+pub mod slots;
@@ -0,0 +1,13 @@
+use std::fs;
+use std::path::Path;
+
+use chip8::Movie;
+
+pub fn read(path: &Path) -> Result<Movie, String> {
+    let data = fs::read(path).map_err(|e| format!("Error reading {}: {}", path.display(), e))?;
+    Movie::from_bytes(&data).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+pub fn write(movie: &Movie, path: &Path) -> Result<(), String> {
+    fs::write(path, movie.to_bytes()).map_err(|e| format!("Error writing {}: {}", path.display(), e))
+}
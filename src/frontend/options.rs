@@ -0,0 +1,210 @@
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use chip8::Quirks;
+
+use crate::frontend::audio::Waveform;
+use crate::frontend::commands::COMMANDS;
+use crate::frontend::crt::Effects;
+use crate::frontend::filter::FilterMode;
+use crate::frontend::layout::Scaling;
+use crate::frontend::palette::{self, Palette};
+
+pub struct Options {
+    pub scale: usize,
+    pub speed: usize,
+    pub rom_path: String,
+    pub quirks: Quirks,
+    pub volume: u8,
+    pub tone_frequency: f32,
+    pub waveform: Waveform,
+    pub seed: Option<u64>,
+    pub load_state: Option<String>,
+    pub rewind_seconds: usize,
+    pub record: Option<String>,
+    pub play: Option<String>,
+    pub debug: bool,
+    pub gdb: Option<String>,
+    pub trace: Option<String>,
+    pub trace_range: Option<RangeInclusive<u16>>,
+    pub trace_ring: Option<usize>,
+    pub config: Option<String>,
+    pub palette: Option<Palette>,
+    pub filter: Option<FilterMode>,
+    pub crt: Option<Effects>,
+    pub scaling: Scaling,
+}
+
+impl Options {
+    pub fn usage(program: &str) -> String {
+        let presets: Vec<&str> = Quirks::PRESETS.iter().map(|(name, _)| *name).collect();
+        let palettes: Vec<&str> = palette::PRESETS.iter().map(|(name, _)| *name).collect();
+        let mut lines = vec![format!("Usage: {} [options] <Scale> <Speed> <ROM>", program)];
+        lines.extend(COMMANDS.iter().map(|command| format!("       {} {}", program, command.usage)));
+        lines.extend([
+            "Speed is the number of instructions executed per 60 Hz frame.".to_string(),
+            "Options:".to_string(),
+            format!("  --quirks <{}>", presets.join("|")),
+            "                                    Instruction quirks of the platform the rom was written for (default: modern)"
+                .to_string(),
+            "  --volume <0-100>                  Beeper volume in percent (default: 25)".to_string(),
+            "  --tone <Hz>                       Beeper frequency (default: 440)".to_string(),
+            "  --waveform <square|sine>          Beeper waveform (default: square)".to_string(),
+            "  --seed <number>                   Seed for the random number generator, for reproducible runs".to_string(),
+            "  --load-state <file>               Restore a save state after loading the rom".to_string(),
+            "  --rewind <seconds>                Length of the rewind history, 0 disables rewinding (default: 30)".to_string(),
+            "  --record <file>                   Record the keypad input into a movie file".to_string(),
+            "  --play <file>                     Replay a recorded movie, using its quirks, seed and speed".to_string(),
+            "  --debug                           Start paused with a debugger reading commands from the terminal".to_string(),
+            "  --gdb <address>                   Start paused and wait for gdb on a TCP address, e.g. 127.0.0.1:1234".to_string(),
+            format!("  --palette <{}|<colors>>", palettes.join("|")),
+            "                                    Display colors, or 2 or 4 hex colors like #000000,#33FF66 (default: classic)"
+                .to_string(),
+            "  --filter <phosphor[:<decay>]|or[:<frames>]>".to_string(),
+            "                                    Reduce flicker by fading out pixels (default decay: 0.6) or by combining the last frames"
+                .to_string(),
+            format!("  --crt <{}|all>", Effects::NAMES.join(",")),
+            "                                    CRT effects, separated by commas (default: scanlines,bloom,vignette)".to_string(),
+            "  --scaling <fit|integer>           Scale the display as large as fits or by whole multiples (default: fit)"
+                .to_string(),
+            "  --config <file>                   Key bindings and palettes to use, see the readme for the format".to_string(),
+            "  --trace <file>                    Log every executed instruction and the registers it changed".to_string(),
+            "  --trace-range <start>-<end>       Only trace instructions between two hex addresses, e.g. 200-2FF".to_string(),
+            "  --trace-ring <count>              Only write the last <count> instructions when the cpu halts on an error"
+                .to_string(),
+        ]);
+        lines.join("\n")
+    }
+
+    pub fn parse(args: &[String]) -> Result<Options, String> {
+        let mut positional = Vec::new();
+        let mut quirks = Quirks::default();
+        let mut volume = 25;
+        let mut tone_frequency = 440.0;
+        let mut waveform = Waveform::Square;
+        let mut seed = None;
+        let mut load_state = None;
+        let mut rewind_seconds = 30;
+        let mut record = None;
+        let mut play = None;
+        let mut debug = false;
+        let mut gdb = None;
+        let mut trace = None;
+        let mut trace_range = None;
+        let mut trace_ring = None;
+        let mut config = None;
+        let mut palette = None;
+        let mut filter = None;
+        let mut crt = None;
+        let mut scaling = Scaling::Fit;
+
+        let mut iter = args.iter().skip(1);
+        while let Some(arg) = iter.next() {
+            if let Some(flag) = arg.strip_prefix("--") {
+                let mut value = || iter.next().ok_or(format!("Missing value for --{}", flag));
+                match flag {
+                    "quirks" => {
+                        let name = value()?;
+                        quirks = Quirks::from_name(name).ok_or(format!("Unknown quirks preset '{}'", name))?;
+                    }
+                    "volume" => {
+                        volume = parse_value(flag, value()?)?;
+                        if volume > 100 {
+                            return Err("--volume must be between 0 and 100".to_string());
+                        }
+                    }
+                    "tone" => tone_frequency = parse_value(flag, value()?)?,
+                    "waveform" => {
+                        let name = value()?;
+                        waveform = Waveform::from_name(name).ok_or(format!("Unknown waveform '{}'", name))?;
+                    }
+                    "seed" => seed = Some(parse_value(flag, value()?)?),
+                    "load-state" => load_state = Some(value()?.clone()),
+                    "rewind" => rewind_seconds = parse_value(flag, value()?)?,
+                    "record" => record = Some(value()?.clone()),
+                    "play" => play = Some(value()?.clone()),
+                    "debug" => debug = true,
+                    "gdb" => gdb = Some(value()?.clone()),
+                    "palette" => palette = Some(Palette::parse(value()?)?),
+                    "filter" => filter = Some(FilterMode::parse(value()?)?),
+                    "crt" => crt = Some(Effects::parse(value()?)?),
+                    "scaling" => {
+                        let name = value()?;
+                        scaling = Scaling::from_name(name).ok_or(format!("Unknown scaling '{}'", name))?;
+                    }
+                    "config" => config = Some(value()?.clone()),
+                    "trace" => trace = Some(value()?.clone()),
+                    "trace-range" => {
+                        let range = value()?;
+                        trace_range =
+                            Some(parse_address_range(range).ok_or(format!("Invalid address range '{}'", range))?);
+                    }
+                    "trace-ring" => trace_ring = Some(parse_value(flag, value()?)?),
+                    _ => return Err(format!("Unknown option --{}", flag)),
+                }
+            } else {
+                positional.push(arg);
+            }
+        }
+
+        if (record.is_some() || play.is_some()) && load_state.is_some() {
+            return Err("--load-state can't be combined with movies".to_string());
+        }
+        if (record.is_some() || play.is_some()) && (debug || gdb.is_some()) {
+            return Err("--debug and --gdb can't be combined with movies".to_string());
+        }
+        if trace.is_none() && (trace_range.is_some() || trace_ring.is_some()) {
+            return Err("--trace-range and --trace-ring require --trace".to_string());
+        }
+        if record.is_some() && play.is_some() {
+            return Err("--record and --play can't be combined".to_string());
+        }
+
+        if positional.len() != 3 {
+            return Err("Expected <Scale> <Speed> <ROM>".to_string());
+        }
+
+        Ok(Options {
+            scale: str::parse::<usize>(positional[0]).map_err(|e| e.to_string())?,
+            speed: str::parse::<usize>(positional[1]).map_err(|e| e.to_string())?,
+            rom_path: positional[2].clone(),
+            quirks,
+            volume,
+            tone_frequency,
+            waveform,
+            seed,
+            load_state,
+            rewind_seconds,
+            record,
+            play,
+            debug,
+            gdb,
+            trace,
+            trace_range,
+            trace_ring,
+            config,
+            palette,
+            filter,
+            crt,
+            scaling,
+        })
+    }
+}
+
+fn parse_value<T: FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse::<T>()
+        .map_err(|_| format!("Invalid value '{}' for --{}", value, flag))
+}
+
+/// Parses two hexadecimal addresses separated by a dash, e.g. `200-2FF`.
+fn parse_address_range(text: &str) -> Option<RangeInclusive<u16>> {
+    let (start, end) = text.split_once('-')?;
+    let address = |text: &str| {
+        let text = text.trim();
+        let hex = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")).unwrap_or(text);
+        u16::from_str_radix(hex, 16).ok()
+    };
+    let (start, end) = (address(start)?, address(end)?);
+    (start <= end).then_some(start..=end)
+}
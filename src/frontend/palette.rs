@@ -0,0 +1,83 @@
+/// RGBA colors for each combination of lit XO-CHIP planes: none, the first, the second and both.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    pub name: String,
+    pub colors: [u32; 4],
+}
+
+pub const PRESETS: [(&str, [u32; 4]); 5] = [
+    ("classic", [0x000000FF, 0xFFFFFFFF, 0xAAAAAAFF, 0x555555FF]),
+    ("green", [0x0A140AFF, 0x33FF66FF, 0x22AA44FF, 0x115522FF]),
+    ("amber", [0x140C00FF, 0xFFB000FF, 0xAA7500FF, 0x553B00FF]),
+    ("gameboy", [0x9BBC0FFF, 0x0F380FFF, 0x306230FF, 0x8BAC0FFF]),
+    ("high-contrast", [0x000000FF, 0xFFFFFFFF, 0xFFFF00FF, 0x00FFFFFF]),
+];
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::presets().remove(0)
+    }
+}
+
+impl Palette {
+    pub fn presets() -> Vec<Palette> {
+        PRESETS
+            .iter()
+            .map(|(name, colors)| Palette { name: name.to_string(), colors: *colors })
+            .collect()
+    }
+
+    pub fn from_name(name: &str) -> Option<Palette> {
+        Self::presets().into_iter().find(|palette| palette.name == name)
+    }
+
+    /// Parses a preset name or a comma separated list of hex colors, e.g. `#000000,#33FF66`. With only a background
+    /// and a foreground color the second plane and the overlap are blended from the two.
+    pub fn parse(text: &str) -> Result<Palette, String> {
+        if let Some(palette) = Self::from_name(text) {
+            return Ok(palette);
+        }
+        let colors = text
+            .split(',')
+            .map(|color| {
+                let color = color.trim();
+                let hex = color.strip_prefix('#').unwrap_or(color);
+                match u32::from_str_radix(hex, 16) {
+                    Ok(rgb) if hex.len() == 6 => Ok(rgb << 8 | 0xFF),
+                    _ => Err(format!("Invalid color '{}', expected #RRGGBB", color)),
+                }
+            })
+            .collect::<Result<Vec<u32>, String>>()?;
+        let colors = match colors[..] {
+            [background, foreground] => [
+                background,
+                foreground,
+                blend(background, foreground, 2),
+                blend(background, foreground, 1),
+            ],
+            [background, first, second, both] => [background, first, second, both],
+            _ => return Err(format!("Unknown palette '{}', expected a preset or 2 or 4 colors", text)),
+        };
+        Ok(Palette { name: "custom".to_string(), colors })
+    }
+}
+
+/// Mixes `thirds` thirds of `foreground` into `background`.
+fn blend(background: u32, foreground: u32, thirds: u32) -> u32 {
+    let channel = |shift: u32| {
+        let (background, foreground) = ((background >> shift) & 0xFF, (foreground >> shift) & 0xFF);
+        ((background * (3 - thirds) + foreground * thirds) / 3) << shift
+    };
+    channel(24) | channel(16) | channel(8) | 0xFF
+}
+
+/// Splits an RGBA color into its red, green and blue channels.
+pub fn channels(color: u32) -> [f32; 3] {
+    [24, 16, 8].map(|shift| ((color >> shift) & 0xFF) as f32)
+}
+
+/// Combines red, green and blue channels into an opaque RGBA color, clamping them to 0-255.
+pub fn color(channels: [f32; 3]) -> u32 {
+    let [red, green, blue] = channels.map(|channel| channel.round().clamp(0.0, 255.0) as u32);
+    red << 24 | green << 16 | blue << 8 | 0xFF
+}
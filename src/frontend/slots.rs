@@ -0,0 +1,18 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chip8::Cpu;
+
+/// Save states of a rom are stored next to it as `<rom>.state<slot>`.
+pub fn slot_path(rom_path: &str, slot: u8) -> PathBuf {
+    PathBuf::from(format!("{}.state{}", rom_path, slot))
+}
+
+pub fn save(cpu: &Cpu, path: &Path) -> Result<(), String> {
+    fs::write(path, cpu.save_state()).map_err(|e| format!("Error writing {}: {}", path.display(), e))
+}
+
+pub fn load(cpu: &mut Cpu, path: &Path) -> Result<(), String> {
+    let data = fs::read(path).map_err(|e| format!("Error reading {}: {}", path.display(), e))?;
+    cpu.load_state(&data).map_err(|e| format!("{}: {}", path.display(), e))
+}
@@ -0,0 +1,397 @@
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+
+use crate::{Breakpoint, Cpu, Debugger, MemoryAccess, Register, StopReason, Watchpoint};
+
+/// Registers in the order of the `g` packet and the target description, with their size in bytes.
+const REGISTERS: [(Register, usize); 21] = [
+    (Register::V(0x0), 1),
+    (Register::V(0x1), 1),
+    (Register::V(0x2), 1),
+    (Register::V(0x3), 1),
+    (Register::V(0x4), 1),
+    (Register::V(0x5), 1),
+    (Register::V(0x6), 1),
+    (Register::V(0x7), 1),
+    (Register::V(0x8), 1),
+    (Register::V(0x9), 1),
+    (Register::V(0xA), 1),
+    (Register::V(0xB), 1),
+    (Register::V(0xC), 1),
+    (Register::V(0xD), 1),
+    (Register::V(0xE), 1),
+    (Register::V(0xF), 1),
+    (Register::I, 2),
+    (Register::Pc, 2),
+    (Register::Sp, 1),
+    (Register::Dt, 1),
+    (Register::St, 1),
+];
+
+const SIGINT: u8 = 2;
+const SIGTRAP: u8 = 5;
+const SIGSEGV: u8 = 11;
+
+/// Server for the GDB remote serial protocol, exposing a `Cpu` through a `Debugger`.
+///
+/// Supports reading and writing registers and memory, stepping, continuing, interrupting, software breakpoints and
+/// watchpoints. Registers are V0-VF, I, PC, SP, DT and ST as described by the `target.xml` target description, 16
+/// bit registers are sent little endian like the register packets of other targets. The server never blocks, `poll`
+/// handles whatever the client sent since the last call and should be called once per frame.
+pub struct GdbStub {
+    listener: TcpListener,
+    client: Option<Client>,
+}
+
+struct Client {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+    /// Whether the client resumed the cpu and expects a stop reply.
+    running: bool,
+}
+
+impl GdbStub {
+    pub fn bind<A: ToSocketAddrs>(address: A) -> io::Result<GdbStub> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(GdbStub { listener, client: None })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn connected(&self) -> bool {
+        self.client.is_some()
+    }
+
+    /// Accepts a new client and handles its pending packets. The cpu is paused when a client connects and resumed
+    /// when it disconnects.
+    pub fn poll(&mut self, cpu: &mut Cpu, debugger: &mut Debugger) -> io::Result<()> {
+        if self.client.is_none() {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(true)?;
+                    stream.set_nodelay(true)?;
+                    debugger.pause();
+                    self.client = Some(Client { stream, buffer: Vec::new(), running: false });
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e),
+            }
+        }
+
+        let Some(client) = &mut self.client else {
+            return Ok(());
+        };
+        let result = client.receive().and_then(|open| {
+            while let Some(packet) = client.next_packet()? {
+                client.handle(&packet, cpu, debugger)?;
+            }
+            Ok(open)
+        });
+        match result {
+            Ok(true) => Ok(()),
+            Ok(false) => {
+                self.disconnect(debugger);
+                Ok(())
+            }
+            Err(e) => {
+                self.disconnect(debugger);
+                Err(e)
+            }
+        }
+    }
+
+    /// Sends the stop reply for a pause of the debugger, should be called with every reason returned by
+    /// `Debugger::run_frame`.
+    pub fn report_stop(&mut self, reason: StopReason) -> io::Result<()> {
+        let Some(client) = &mut self.client else {
+            return Ok(());
+        };
+        client.running = false;
+        match reason {
+            StopReason::Exited => client.send("W00"),
+            StopReason::Error(_) => client.send(&format!("S{:02x}", SIGSEGV)),
+            _ => client.send(&format!("S{:02x}", SIGTRAP)),
+        }
+    }
+
+    fn disconnect(&mut self, debugger: &mut Debugger) {
+        self.client = None;
+        debugger.resume();
+    }
+}
+
+impl Client {
+    /// Reads everything available, returns false once the client closed the connection.
+    fn receive(&mut self) -> io::Result<bool> {
+        let mut chunk = [0; 1024];
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Ok(false),
+                Ok(read) => self.buffer.extend_from_slice(&chunk[..read]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(true),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Takes the next complete packet out of the buffer and acknowledges it. An interrupt request (0x03) is returned
+    /// as the packet "\x03".
+    fn next_packet(&mut self) -> io::Result<Option<String>> {
+        loop {
+            let Some(&first) = self.buffer.first() else {
+                return Ok(None);
+            };
+            match first {
+                0x03 => {
+                    self.buffer.remove(0);
+                    return Ok(Some("\x03".to_string()));
+                }
+                b'$' => {
+                    let Some(end) = self.buffer.iter().position(|&b| b == b'#') else {
+                        return Ok(None);
+                    };
+                    if self.buffer.len() < end + 3 {
+                        return Ok(None);
+                    }
+                    let packet: Vec<u8> = self.buffer.drain(..end + 3).collect();
+                    let data = &packet[1..end];
+                    let checksum = std::str::from_utf8(&packet[end + 1..])
+                        .ok()
+                        .and_then(|checksum| u8::from_str_radix(checksum, 16).ok());
+                    if checksum != Some(checksum_of(data)) {
+                        self.stream.write_all(b"-")?;
+                        continue;
+                    }
+                    self.stream.write_all(b"+")?;
+                    return Ok(Some(String::from_utf8_lossy(data).into_owned()));
+                }
+                // Acknowledgements and noise between packets
+                _ => {
+                    self.buffer.remove(0);
+                }
+            }
+        }
+    }
+
+    fn send(&mut self, data: &str) -> io::Result<()> {
+        let packet = format!("${}#{:02x}", data, checksum_of(data.as_bytes()));
+        self.stream.write_all(packet.as_bytes())
+    }
+
+    fn handle(&mut self, packet: &str, cpu: &mut Cpu, debugger: &mut Debugger) -> io::Result<()> {
+        let reply = match packet.as_bytes().first() {
+            Some(0x03) => {
+                if !self.running {
+                    return Ok(());
+                }
+                debugger.pause();
+                self.running = false;
+                format!("S{:02x}", SIGINT)
+            }
+            Some(b'?') => format!("S{:02x}", SIGTRAP),
+            Some(b'g') => REGISTERS
+                .iter()
+                .map(|&(register, size)| encode_register(cpu.register(register), size))
+                .collect(),
+            Some(b'G') => {
+                let mut data = &packet[1..];
+                for &(register, size) in &REGISTERS {
+                    let Some(value) = data.get(..size * 2).and_then(decode_register) else {
+                        break;
+                    };
+                    cpu.set_register(register, value);
+                    data = &data[size * 2..];
+                }
+                "OK".to_string()
+            }
+            Some(b'p') => match parse_hex(&packet[1..]).and_then(|n| REGISTERS.get(n)) {
+                Some(&(register, size)) => encode_register(cpu.register(register), size),
+                None => "E01".to_string(),
+            },
+            Some(b'P') => {
+                let register = packet[1..].split_once('=').and_then(|(n, value)| {
+                    let (register, size) = *REGISTERS.get(parse_hex(n)?)?;
+                    Some((register, decode_register(value.get(..size * 2)?)?))
+                });
+                match register {
+                    Some((register, value)) => {
+                        cpu.set_register(register, value);
+                        "OK".to_string()
+                    }
+                    None => "E01".to_string(),
+                }
+            }
+            Some(b'm') => match parse_range(&packet[1..]).and_then(|(start, end)| cpu.memory().get(start..end)) {
+                Some(bytes) => bytes.iter().map(|byte| format!("{:02x}", byte)).collect(),
+                None => "E01".to_string(),
+            },
+            Some(b'M') => {
+                let write = packet[1..].split_once(':').and_then(|(range, data)| {
+                    let (start, end) = parse_range(range)?;
+                    let bytes = decode_hex(data)?;
+                    (bytes.len() == end - start).then_some((start, bytes))
+                });
+                let memory = cpu.memory_mut();
+                match write.filter(|(start, bytes)| start + bytes.len() <= memory.len()) {
+                    Some((start, bytes)) => {
+                        memory[start..start + bytes.len()].copy_from_slice(&bytes);
+                        "OK".to_string()
+                    }
+                    None => "E01".to_string(),
+                }
+            }
+            Some(b'c') => {
+                if let Some(address) = parse_hex(&packet[1..]) {
+                    cpu.set_register(Register::Pc, address as u16);
+                }
+                debugger.resume();
+                self.running = true;
+                return Ok(());
+            }
+            Some(b's') => {
+                if let Some(address) = parse_hex(&packet[1..]) {
+                    cpu.set_register(Register::Pc, address as u16);
+                }
+                debugger.step();
+                self.running = true;
+                return Ok(());
+            }
+            Some(b'Z') | Some(b'z') => self.breakpoint(packet, debugger),
+            Some(b'D') => {
+                self.send("OK")?;
+                return self.stream.shutdown(std::net::Shutdown::Both);
+            }
+            Some(b'k') => return self.stream.shutdown(std::net::Shutdown::Both),
+            Some(b'H') => "OK".to_string(),
+            Some(b'q') => self.query(&packet[1..]),
+            _ => String::new(),
+        };
+        self.send(&reply)
+    }
+
+    /// Handles `Z`/`z` packets, type 0 and 1 are breakpoints and 2-4 the write, read and access watchpoints.
+    fn breakpoint(&self, packet: &str, debugger: &mut Debugger) -> String {
+        let insert = packet.starts_with('Z');
+        let mut fields = packet[1..].split(',');
+        let (Some(kind), Some(address), Some(length)) = (fields.next(), fields.next(), fields.next()) else {
+            return "E01".to_string();
+        };
+        let (Some(address), Some(length)) = (parse_hex(address), parse_hex(length)) else {
+            return "E01".to_string();
+        };
+        let access = match kind {
+            "0" | "1" => {
+                if insert {
+                    debugger.add_breakpoint(Breakpoint { address: address as u16, condition: None });
+                } else {
+                    debugger.remove_breakpoint(address as u16);
+                }
+                return "OK".to_string();
+            }
+            "2" => Some(MemoryAccess::Write),
+            "3" => Some(MemoryAccess::Read),
+            "4" => None,
+            _ => return String::new(),
+        };
+
+        let Some(end) = address.checked_add(length.max(1) - 1) else {
+            return "E01".to_string();
+        };
+        let watchpoint = Watchpoint::Memory { start: address, end, access };
+        if insert {
+            debugger.add_watchpoint(watchpoint);
+        } else if let Some(index) = debugger.watchpoints().iter().position(|w| *w == watchpoint) {
+            debugger.remove_watchpoint(index);
+        }
+        "OK".to_string()
+    }
+
+    fn query(&self, query: &str) -> String {
+        if query.starts_with("Supported") {
+            return "PacketSize=4000;qXfer:features:read+".to_string();
+        }
+        if let Some(range) = query.strip_prefix("Xfer:features:read:target.xml:") {
+            let Some((offset, length)) = range.split_once(',') else {
+                return "E01".to_string();
+            };
+            let (Some(offset), Some(length)) = (parse_hex(offset), parse_hex(length)) else {
+                return "E01".to_string();
+            };
+            let xml = target_description();
+            let start = offset.min(xml.len());
+            let end = offset.saturating_add(length).min(xml.len());
+            let prefix = if end == xml.len() { 'l' } else { 'm' };
+            return format!("{}{}", prefix, &xml[start..end]);
+        }
+        match query {
+            "Attached" => "1".to_string(),
+            "C" => "QC1".to_string(),
+            "fThreadInfo" => "m1".to_string(),
+            "sThreadInfo" => "l".to_string(),
+            _ => String::new(),
+        }
+    }
+}
+
+fn target_description() -> String {
+    let registers: String = REGISTERS
+        .iter()
+        .map(|&(register, size)| {
+            let kind = match register {
+                Register::I => "data_ptr",
+                Register::Pc => "code_ptr",
+                _ => "uint8",
+            };
+            format!(
+                "    <reg name=\"{}\" bitsize=\"{}\" type=\"{}\"/>\n",
+                register.to_string().to_lowercase(),
+                size * 8,
+                kind
+            )
+        })
+        .collect();
+    format!(
+        "<?xml version=\"1.0\"?>\n<!DOCTYPE target SYSTEM \"gdb-target.dtd\">\n<target version=\"1.0\">\n  \
+         <feature name=\"org.chip8.core\">\n{}  </feature>\n</target>\n",
+        registers
+    )
+}
+
+fn checksum_of(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte))
+}
+
+/// Registers are sent little endian, the byte order gdb assumes without an architecture in the target description.
+fn encode_register(value: u16, size: usize) -> String {
+    value.to_le_bytes()[..size].iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode_register(hex: &str) -> Option<u16> {
+    let bytes = decode_hex(hex)?;
+    Some(bytes.iter().rev().fold(0, |value, &byte| value << 8 | byte as u16))
+}
+
+fn parse_hex(hex: &str) -> Option<usize> {
+    usize::from_str_radix(hex, 16).ok()
+}
+
+/// Parses `addr,length` into a start and end address.
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    let (start, length) = range.split_once(',')?;
+    let start = parse_hex(start)?;
+    Some((start, start.checked_add(parse_hex(length)?)?))
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
@@ -0,0 +1,30 @@
+mod asm;
+mod cpu;
+mod debugger;
+mod disasm;
+mod error;
+mod gdb;
+mod movie;
+mod opcodes;
+mod quirks;
+mod random;
+mod rewind;
+mod state;
+mod trace;
+
+pub use crate::asm::assemble;
+pub use crate::cpu::{Cpu, MemoryAccess, MemoryHook, Register, StepInfo};
+pub use crate::debugger::{Breakpoint, Comparison, Condition, Debugger, StopReason, Watchpoint};
+pub use crate::disasm::{disassemble, disassemble_at, disassemble_rom, Instruction, ListingLine};
+pub use crate::error::{AsmError, AsmErrorKind, CpuError, CpuErrorKind, LoadError, MovieError, StateError};
+pub use crate::gdb::GdbStub;
+pub use crate::movie::Movie;
+pub use crate::quirks::{IndexIncrement, Quirks};
+pub use crate::random::{RandomSource, XorShiftRng};
+pub use crate::rewind::Rewind;
+pub use crate::trace::{Trace, TraceEntry};
+
+pub const VIDEO_WIDTH: usize = 64;
+pub const VIDEO_HEIGHT: usize = 32;
+pub const HIRES_VIDEO_WIDTH: usize = 128;
+pub const HIRES_VIDEO_HEIGHT: usize = 64;
@@ -1,81 +1,423 @@
-use crate::cpu::Cpu;
+use crate::frontend::audio::Beeper;
+use crate::frontend::commands::{self, CommandError};
+use crate::frontend::config::Config;
+use crate::frontend::crt::Crt;
+use crate::frontend::debug;
+use crate::frontend::filter::Filter;
+use crate::frontend::keymap::KeyMap;
+use crate::frontend::layout;
+use crate::frontend::movies;
+use crate::frontend::options::Options;
+use crate::frontend::palette::Palette;
+use crate::frontend::slots;
+use chip8::{Cpu, Debugger, GdbStub, LoadError, Movie, Rewind, StopReason, Trace, VIDEO_HEIGHT, VIDEO_WIDTH};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Mod};
 use sdl2::pixels::PixelFormatEnum;
+use sdl2::video::FullscreenType;
+use sdl2::EventPump;
 use std::env;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
-mod cpu;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
 
-const VIDEO_WIDTH: usize = 64;
-const VIDEO_HEIGHT: usize = 32;
+mod frontend;
+
+const FRAME_DURATION: Duration = Duration::from_micros(1_000_000 / 60);
 
 fn main() -> Result<(), String> {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() < 4 {
-        println!("Usage: {} <Scale> <Delay> <ROM>", args[0]);
+    if let Some(command) = args.get(1).and_then(|name| commands::find(name)) {
+        match (command.run)(&args[2..]) {
+            Ok(()) => return Ok(()),
+            Err(CommandError::Usage(e)) => eprintln!("{}\nUsage: {} {}", e, args[0], command.usage),
+            Err(CommandError::Failed(e)) => eprintln!("{}", e),
+        }
+        process::exit(1);
     }
-    let scale = str::parse::<usize>(&args[1]).map_err(|e| e.to_string())?;
-    let delay = str::parse::<usize>(&args[2]).map_err(|e| e.to_string())?;
-    let rom_path = &args[3];
+
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, Options::usage(&args[0]));
+            process::exit(1);
+        }
+    };
+    let scale = options.scale;
+    let rom_path = &options.rom_path;
 
     let sdl_context = sdl2::init()?;
     let window = sdl_context
         .video()?
         .window("Chip8 Emulator", (VIDEO_WIDTH * scale) as u32, (VIDEO_HEIGHT * scale) as u32)
         .position_centered()
+        .resizable()
         .build()
         .map_err(|e| e.to_string())?;
 
     let mut canvas = window
         .into_canvas()
         .software()
+        .present_vsync()
         .build()
         .map_err(|e| e.to_string())?;
 
-    let mut cpu = Cpu::new(rom_path);
+    let rom = fs::read(rom_path).map_err(|e| format!("{}: {}", rom_path, LoadError::from(e)))?;
+    let mut playback = match &options.play {
+        Some(movie_path) => Some(movies::read(Path::new(movie_path))?),
+        None => None,
+    };
+    let mut cpu = match &playback {
+        Some(movie) => movie.create_cpu(&rom).map_err(|e| e.to_string())?,
+        None => {
+            let mut cpu = Cpu::with_quirks(options.quirks);
+            if let Some(seed) = options.seed {
+                cpu.set_seed(seed);
+            }
+            cpu.load_rom(&rom).map_err(|e| format!("{}: {}", rom_path, e))?;
+            cpu
+        }
+    };
+    let speed = playback
+        .as_ref()
+        .map_or(options.speed, |movie| movie.instructions_per_frame as usize);
+    let mut recording = options
+        .record
+        .as_ref()
+        .map(|_| Movie::new(&rom, options.quirks, cpu.seed(), speed as u32));
+    if let Some(state_path) = &options.load_state {
+        slots::load(&mut cpu, Path::new(state_path))?;
+    }
+    if let Some(trace_path) = &options.trace {
+        let file = File::create(trace_path).map_err(|e| format!("{}: {}", trace_path, e))?;
+        let mut trace = Trace::new(BufWriter::new(file));
+        if let Some(range) = &options.trace_range {
+            trace.set_address_range(range.clone());
+        }
+        if let Some(capacity) = options.trace_ring {
+            trace.set_ring_buffer(capacity);
+        }
+        cpu.set_trace(Some(trace));
+    }
     let texture_creator = canvas.texture_creator();
+    let mut texture = texture_creator
+        .create_texture_streaming(PixelFormatEnum::RGBA8888, VIDEO_WIDTH as u32, VIDEO_HEIGHT as u32)
+        .map_err(|e| e.to_string())?;
 
     let mut event_pump = sdl_context.event_pump()?;
+    let config = match &options.config {
+        Some(config_path) => Config::load(Path::new(config_path), rom_path)?,
+        None => Config::default(),
+    };
+    let key_map = config.key_map;
+    let mut beeper = match sdl_context
+        .audio()
+        .and_then(|audio| Beeper::new(&audio, options.tone_frequency, options.volume, options.waveform))
+    {
+        Ok(beeper) => Some(beeper),
+        Err(e) => {
+            eprintln!("Audio unavailable, running without sound: {}", e);
+            None
+        }
+    };
 
-    let mut last_cycle_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let mut debugger = (options.debug || options.gdb.is_some()).then(Debugger::new);
+    let debug_commands = options.debug.then(debug::spawn_repl);
+    let mut gdb = match &options.gdb {
+        Some(address) => {
+            let gdb = GdbStub::bind(address).map_err(|e| format!("Error binding {}: {}", address, e))?;
+            println!("Waiting for gdb to connect to {}", address);
+            Some(gdb)
+        }
+        None => None,
+    };
+    let mut palettes = Palette::presets();
+    palettes.extend(config.palettes);
+    let initial_palette = options.palette.or(config.palette).unwrap_or_default();
+    let mut palette = match palettes.iter().position(|palette| *palette == initial_palette) {
+        Some(index) => index,
+        None => {
+            palettes.push(initial_palette);
+            palettes.len() - 1
+        }
+    };
+    let mut filter = Filter::new(options.filter.unwrap_or_default(), options.filter.is_some());
+    let mut crt = Crt::new(options.crt.unwrap_or_default(), options.crt.is_some());
+    let mut redraw = true;
+    let mut rewind = Rewind::new(options.rewind_seconds);
+    let mut rewinding = false;
+    let mut halted = false;
+    let mut frame = 0;
+    let mut next_frame = Instant::now();
     'running: loop {
-        if cpu.process_input(&mut event_pump) {
-            break 'running;
+        for hotkey in process_input(&mut cpu, &key_map, &mut event_pump) {
+            match hotkey {
+                Hotkey::Quit => break 'running,
+                Hotkey::ToggleMute => {
+                    if let Some(beeper) = &mut beeper {
+                        beeper.toggle_mute();
+                    }
+                }
+                Hotkey::NextPalette => {
+                    palette = (palette + 1) % palettes.len();
+                    println!("Palette: {}", palettes[palette].name);
+                    redraw = true;
+                }
+                Hotkey::ToggleFilter => {
+                    println!("Flicker filter {}", if filter.toggle() { "on" } else { "off" });
+                    redraw = true;
+                }
+                Hotkey::ToggleCrt => {
+                    println!("CRT effects {}", if crt.toggle() { "on" } else { "off" });
+                    redraw = true;
+                }
+                Hotkey::ToggleFullscreen => {
+                    let window = canvas.window_mut();
+                    let fullscreen = match window.fullscreen_state() {
+                        FullscreenType::Off => FullscreenType::Desktop,
+                        _ => FullscreenType::Off,
+                    };
+                    window.set_fullscreen(fullscreen)?;
+                    redraw = true;
+                }
+                Hotkey::Redraw => redraw = true,
+                Hotkey::Rewind(_) if playback.is_some() || recording.is_some() => {
+                    eprintln!("Rewinding is disabled while a movie is recording or playing");
+                }
+                Hotkey::Rewind(held) => rewinding = held,
+                Hotkey::SaveState(slot) => match slots::save(&cpu, &slots::slot_path(rom_path, slot)) {
+                    Ok(()) => println!("Saved state to slot {}", slot),
+                    Err(e) => eprintln!("{}", e),
+                },
+                Hotkey::LoadState(_) if playback.is_some() || recording.is_some() => {
+                    eprintln!("Save states can't be loaded while a movie is recording or playing");
+                }
+                Hotkey::LoadState(slot) => match slots::load(&mut cpu, &slots::slot_path(rom_path, slot)) {
+                    Ok(()) => {
+                        println!("Loaded state from slot {}", slot);
+                        rewind.clear();
+                        if halted {
+                            halted = false;
+                            canvas.window_mut().set_title("Chip8 Emulator").map_err(|e| e.to_string())?;
+                        }
+                    }
+                    Err(e) => eprintln!("{}", e),
+                },
+            }
+        }
+
+        if let (Some(debugger), Some(commands)) = (&mut debugger, &debug_commands) {
+            while let Ok(command) = commands.try_recv() {
+                match command {
+                    Ok(command) => {
+                        if !debug::execute(command, debugger, &cpu) {
+                            break 'running;
+                        }
+                    }
+                    Err(e) => println!("{}", e),
+                }
+            }
+        }
+
+        if let (Some(gdb), Some(debugger)) = (&mut gdb, &mut debugger) {
+            if let Err(e) = gdb.poll(&mut cpu, debugger) {
+                eprintln!("gdb connection closed: {}", e);
+            }
         }
 
-        let current_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-        let dt = current_time - last_cycle_time;
+        if rewinding {
+            if rewind.rewind(&mut cpu) && halted {
+                halted = false;
+                canvas.window_mut().set_title("Chip8 Emulator").map_err(|e| e.to_string())?;
+            }
+        } else if !halted && !debugger.as_ref().is_some_and(Debugger::paused) {
+            if let Some(movie) = &playback {
+                if !movie.apply_frame(frame, &mut cpu) {
+                    println!("Movie finished after {} frames", frame);
+                    playback = None;
+                }
+            }
+            if let Some(movie) = &mut recording {
+                movie.record_frame(cpu.keypad());
+            }
+            frame += 1;
 
-        if dt > Duration::from_millis(delay as u64) {
-            last_cycle_time = current_time;
+            let result = match &mut debugger {
+                Some(debugger) => match debugger.run_frame(&mut cpu, speed) {
+                    Some(reason) => {
+                        if debug_commands.is_some() {
+                            debug::print_stop(reason, &cpu, debugger);
+                        }
+                        if let Some(gdb) = &mut gdb {
+                            if let Err(e) = gdb.report_stop(reason) {
+                                eprintln!("gdb connection closed: {}", e);
+                            }
+                        }
+                        match reason {
+                            StopReason::Error(e) => Err(e),
+                            _ => Ok(()),
+                        }
+                    }
+                    None => Ok(()),
+                },
+                None => cpu.run_frame(speed),
+            };
+            if let Err(e) = result {
+                eprintln!("Cpu halted: {}", e);
+                canvas
+                    .window_mut()
+                    .set_title(&format!("Chip8 Emulator - halted: {}", e))
+                    .map_err(|e| e.to_string())?;
+                halted = true;
+            } else {
+                rewind.push(&cpu);
+            }
+            // Keep the debugger around to inspect the final state
+            if cpu.exited() && debugger.is_none() {
+                break 'running;
+            }
+        }
+        if let Some(beeper) = &mut beeper {
+            beeper.update(
+                if halted { 0 } else { cpu.sound_timer() },
+                cpu.audio_pattern(),
+                cpu.audio_pattern_rate(),
+            );
+        }
 
-            cpu.cycle();
-            let mut texture = texture_creator
-                .create_texture_streaming(PixelFormatEnum::RGBA8888, 64, 32)
-                .map_err(|e| e.to_string())?;
-            // Create a red-green gradient
+        // Only upload and present frames that look different, the flicker filter changes every frame while pixels
+        // fade out
+        if cpu.take_display_changed() || filter.enabled() || redraw {
+            redraw = false;
+            let pixels = filter.apply(cpu.video(), &palettes[palette].colors);
+            let viewport = layout::viewport(
+                canvas.output_size()?,
+                (cpu.width() as u32, cpu.height() as u32),
+                options.scaling,
+            );
+            // Effects are applied close to the size on screen, so hires is scaled up half as much
+            let factor = (viewport.width() as usize / cpu.width()).max(1);
+            let (pixels, width, height) = crt.apply(pixels, cpu.width(), cpu.height(), factor);
+            let query = texture.query();
+            if (query.width as usize, query.height as usize) != (width, height) {
+                texture = texture_creator
+                    .create_texture_streaming(PixelFormatEnum::RGBA8888, width as u32, height as u32)
+                    .map_err(|e| e.to_string())?;
+            }
             texture.with_lock(None, |buffer: &mut [u8], pitch: usize| {
-                let video = cpu.video;
-                for y in 0..VIDEO_HEIGHT {
-                    for x in 0..VIDEO_WIDTH {
+                for y in 0..height {
+                    for x in 0..width {
                         let offset = y * pitch + x * 4;
-                        let v = video[y * VIDEO_WIDTH + x];
-                        let r= ((v >> 24) & 0xFF) as u8;
-                        let g= ((v >> 16) & 0xFF) as u8;
-                        let b= ((v >> 8) & 0xFF) as u8;
-                        let a= ((v) & 0xFF) as u8;
-
-                        buffer[offset] = r;
-                        buffer[offset + 1] = g;
-                        buffer[offset + 2] = b;
-                        buffer[offset + 3] = a;
+                        buffer[offset..offset + 4].copy_from_slice(&pixels[y * width + x].to_ne_bytes());
                     }
                 }
             })?;
 
             canvas.clear();
-            canvas.copy(&texture, None, None)?;
+            canvas.copy(&texture, None, viewport)?;
             canvas.present();
         }
+
+        next_frame += FRAME_DURATION;
+        let now = Instant::now();
+        if next_frame > now {
+            thread::sleep(next_frame - now);
+        } else {
+            // Running behind, don't try to catch up on missed frames
+            next_frame = now;
+        }
+    }
+
+    if let (Some(movie), Some(movie_path)) = (&recording, &options.record) {
+        movies::write(movie, Path::new(movie_path))?;
+        println!("Recorded {} frames to {}", movie.frames.len(), movie_path);
+    }
+    if let (Some(trace), Some(trace_path)) = (cpu.take_trace(), &options.trace) {
+        trace.finish().map_err(|e| format!("{}: {}", trace_path, e))?;
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+enum Hotkey {
+    Quit,
+    ToggleMute,
+    NextPalette,
+    ToggleFilter,
+    ToggleCrt,
+    ToggleFullscreen,
+    /// The window needs to be drawn again, e.g. after being uncovered.
+    Redraw,
+    SaveState(u8),
+    LoadState(u8),
+    Rewind(bool),
+}
+
+fn process_input(cpu: &mut Cpu, key_map: &KeyMap, event_pump: &mut EventPump) -> Vec<Hotkey> {
+    let mut hotkeys = Vec::new();
+    for event in event_pump.poll_iter() {
+        match event {
+            Event::Quit { .. } => {
+                hotkeys.push(Hotkey::Quit);
+            }
+            Event::Window { win_event: WindowEvent::Exposed | WindowEvent::SizeChanged(..), .. } => {
+                hotkeys.push(Hotkey::Redraw);
+            }
+            Event::KeyDown { keycode: Some(keycode), keymod, .. } => {
+                if let Some(slot) = state_slot(keycode) {
+                    if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                        hotkeys.push(Hotkey::SaveState(slot));
+                    } else {
+                        hotkeys.push(Hotkey::LoadState(slot));
+                    }
+                    continue;
+                }
+                match keycode {
+                    Keycode::Escape => hotkeys.push(Hotkey::Quit),
+                    Keycode::F11 => hotkeys.push(Hotkey::ToggleFullscreen),
+                    Keycode::Return if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) => {
+                        hotkeys.push(Hotkey::ToggleFullscreen);
+                    }
+                    Keycode::M => hotkeys.push(Hotkey::ToggleMute),
+                    Keycode::F9 => hotkeys.push(Hotkey::NextPalette),
+                    Keycode::F10 => hotkeys.push(Hotkey::ToggleFilter),
+                    Keycode::F12 => hotkeys.push(Hotkey::ToggleCrt),
+                    Keycode::Backspace => hotkeys.push(Hotkey::Rewind(true)),
+                    _ => {
+                        if let Some(key) = key_map.key(keycode) {
+                            cpu.set_key(key, true);
+                        }
+                    }
+                }
+            }
+            Event::KeyUp { keycode: Some(keycode), .. } => match keycode {
+                Keycode::Backspace => hotkeys.push(Hotkey::Rewind(false)),
+                _ => {
+                    if let Some(key) = key_map.key(keycode) {
+                        cpu.set_key(key, false);
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+    hotkeys
+}
+
+/// F1-F8 load the save state slots 1-8, or save them with shift held.
+fn state_slot(keycode: Keycode) -> Option<u8> {
+    match keycode {
+        Keycode::F1 => Some(1),
+        Keycode::F2 => Some(2),
+        Keycode::F3 => Some(3),
+        Keycode::F4 => Some(4),
+        Keycode::F5 => Some(5),
+        Keycode::F6 => Some(6),
+        Keycode::F7 => Some(7),
+        Keycode::F8 => Some(8),
+        _ => None,
+    }
+}
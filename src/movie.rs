@@ -0,0 +1,154 @@
+use crate::state::StateReader;
+use crate::{Cpu, IndexIncrement, MovieError, Quirks};
+
+const MAGIC: &[u8; 4] = b"C8MV";
+const VERSION: u16 = 1;
+
+/// Recording of the keypad state of every frame, together with everything else needed to replay a run bit-exactly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movie {
+    pub quirks: Quirks,
+    pub seed: u64,
+    pub instructions_per_frame: u32,
+    pub rom_hash: u64,
+    /// One bit per key for every frame, bit n set while key n is held.
+    pub frames: Vec<u16>,
+}
+
+impl Movie {
+    pub fn new(rom: &[u8], quirks: Quirks, seed: u64, instructions_per_frame: u32) -> Self {
+        Self {
+            quirks,
+            seed,
+            instructions_per_frame,
+            rom_hash: hash(rom),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Records the keypad of the next frame, call before the frame is run.
+    pub fn record_frame(&mut self, keypad: &[u8; 16]) {
+        let keys = keypad
+            .iter()
+            .enumerate()
+            .fold(0, |keys, (key, &pressed)| keys | ((pressed != 0) as u16) << key);
+        self.frames.push(keys);
+    }
+
+    /// Sets the keypad of `cpu` to the recorded state of `frame`. Returns false when the movie has ended.
+    pub fn apply_frame(&self, frame: usize, cpu: &mut Cpu) -> bool {
+        let Some(keys) = self.frames.get(frame) else {
+            return false;
+        };
+        for key in 0..16 {
+            cpu.set_key(key, (keys >> key) & 1 != 0);
+        }
+        true
+    }
+
+    /// Creates a cpu set up exactly like the one the movie was recorded with.
+    pub fn create_cpu(&self, rom: &[u8]) -> Result<Cpu, MovieError> {
+        if hash(rom) != self.rom_hash {
+            return Err(MovieError::RomMismatch);
+        }
+        let mut cpu = Cpu::with_quirks(self.quirks);
+        cpu.set_seed(self.seed);
+        cpu.load_rom(rom)?;
+        Ok(cpu)
+    }
+
+    /// Replays the whole movie without any frontend and returns the final machine.
+    pub fn play(&self, rom: &[u8]) -> Result<Cpu, MovieError> {
+        let mut cpu = self.create_cpu(rom)?;
+        for frame in 0..self.frames.len() {
+            self.apply_frame(frame, &mut cpu);
+            cpu.run_frame(self.instructions_per_frame as usize)
+                .map_err(|error| MovieError::Cpu { frame, error })?;
+        }
+        Ok(cpu)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(64 + self.frames.len() * 2);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+
+        let quirks = &self.quirks;
+        out.push(quirks.shift_uses_vy as u8);
+        out.push(match quirks.load_store_index {
+            IndexIncrement::Unchanged => 0,
+            IndexIncrement::ByX => 1,
+            IndexIncrement::ByXPlusOne => 2,
+        });
+        out.push(quirks.jump_uses_vx as u8);
+        out.push(quirks.logic_resets_vf as u8);
+        out.push(quirks.sprite_wrap as u8);
+        out.push(quirks.display_wait as u8);
+        out.extend_from_slice(&(quirks.memory_size as u32).to_le_bytes());
+
+        out.extend_from_slice(&self.seed.to_le_bytes());
+        out.extend_from_slice(&self.instructions_per_frame.to_le_bytes());
+        out.extend_from_slice(&self.rom_hash.to_le_bytes());
+
+        out.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        for keys in &self.frames {
+            out.extend_from_slice(&keys.to_le_bytes());
+        }
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Movie, MovieError> {
+        let mut reader = StateReader::new(data, || MovieError::Truncated);
+        if reader.bytes(MAGIC.len())? != MAGIC {
+            return Err(MovieError::InvalidMagic);
+        }
+        let version = reader.u16()?;
+        if version != VERSION {
+            return Err(MovieError::UnsupportedVersion(version));
+        }
+
+        let quirks = Quirks {
+            shift_uses_vy: reader.bool()?,
+            load_store_index: match reader.u8()? {
+                0 => IndexIncrement::Unchanged,
+                1 => IndexIncrement::ByX,
+                2 => IndexIncrement::ByXPlusOne,
+                _ => return Err(MovieError::InvalidData("index increment quirk")),
+            },
+            jump_uses_vx: reader.bool()?,
+            logic_resets_vf: reader.bool()?,
+            sprite_wrap: reader.bool()?,
+            display_wait: reader.bool()?,
+            memory_size: reader.u32()? as usize,
+        };
+        if !Quirks::MEMORY_SIZES.contains(&quirks.memory_size) {
+            return Err(MovieError::InvalidData("memory size"));
+        }
+
+        let seed = reader.u64()?;
+        let instructions_per_frame = reader.u32()?;
+        let rom_hash = reader.u64()?;
+
+        let frame_count = reader.u32()? as usize;
+        let frames = reader
+            .bytes(frame_count * 2)?
+            .chunks_exact(2)
+            .map(|keys| u16::from_le_bytes([keys[0], keys[1]]))
+            .collect();
+
+        Ok(Movie {
+            quirks,
+            seed,
+            instructions_per_frame,
+            rom_hash,
+            frames,
+        })
+    }
+}
+
+/// 64 bit FNV-1a, only used to make sure a movie is replayed with the rom it was recorded on.
+fn hash(data: &[u8]) -> u64 {
+    data.iter().fold(0xCBF29CE484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001B3)
+    })
+}
@@ -0,0 +1,120 @@
+use crate::cpu::Operation;
+
+/// One row of the instruction set. An opcode belongs to the row when `opcode & mask == pattern`.
+///
+/// `syntax` is the assembly form of the instruction. Words matching one of the operand placeholders are replaced by
+/// the field of the opcode they name, every other word is part of the fixed syntax:
+///
+/// * `Vx`, `Vy` - register in the second or third nibble
+/// * `kk` - low byte
+/// * `nnn` - low 12 bits
+/// * `n` - low nibble
+/// * `p` - 4 bit value in the second nibble (the XO-CHIP plane mask)
+/// * `nnnn` - 16 bit word following the opcode
+pub(crate) struct OpcodeDef {
+    pub(crate) pattern: u16,
+    pub(crate) mask: u16,
+    pub(crate) syntax: &'static str,
+    pub(crate) operation: fn(u16) -> Operation,
+}
+
+macro_rules! opcode {
+    ($pattern:expr, $mask:expr, $syntax:expr, $operation:ident) => {
+        OpcodeDef {
+            pattern: $pattern,
+            mask: $mask,
+            syntax: $syntax,
+            operation: Operation::$operation,
+        }
+    };
+}
+
+pub(crate) const OPCODES: [OpcodeDef; 50] = [
+    opcode!(0x00E0, 0xFFFF, "CLS", Cls00E0),
+    opcode!(0x00EE, 0xFFFF, "RET", Ret00EE),
+    opcode!(0x00C0, 0xFFF0, "SCD n", Scd00Cn),
+    opcode!(0x00D0, 0xFFF0, "SCU n", Scu00Dn),
+    opcode!(0x00FB, 0xFFFF, "SCR", Scr00FB),
+    opcode!(0x00FC, 0xFFFF, "SCL", Scl00FC),
+    opcode!(0x00FD, 0xFFFF, "EXIT", Exit00FD),
+    opcode!(0x00FE, 0xFFFF, "LOW", Low00FE),
+    opcode!(0x00FF, 0xFFFF, "HIGH", High00FF),
+    opcode!(0x1000, 0xF000, "JP nnn", Jp1nnn),
+    opcode!(0x2000, 0xF000, "CALL nnn", Call2nnn),
+    opcode!(0x3000, 0xF000, "SE Vx, kk", Se3xkk),
+    opcode!(0x4000, 0xF000, "SNE Vx, kk", Sne4xkk),
+    opcode!(0x5000, 0xF00F, "SE Vx, Vy", Se5xy0),
+    opcode!(0x5002, 0xF00F, "SAVE Vx, Vy", Save5xy2),
+    opcode!(0x5003, 0xF00F, "LOAD Vx, Vy", Load5xy3),
+    opcode!(0x6000, 0xF000, "LD Vx, kk", Ld6xkk),
+    opcode!(0x7000, 0xF000, "ADD Vx, kk", Add7xkk),
+    opcode!(0x8000, 0xF00F, "LD Vx, Vy", Ld8xy0),
+    opcode!(0x8001, 0xF00F, "OR Vx, Vy", Or8xy1),
+    opcode!(0x8002, 0xF00F, "AND Vx, Vy", And8xy2),
+    opcode!(0x8003, 0xF00F, "XOR Vx, Vy", Xor8xy3),
+    opcode!(0x8004, 0xF00F, "ADD Vx, Vy", Add8xy4),
+    opcode!(0x8005, 0xF00F, "SUB Vx, Vy", Sub8xy5),
+    opcode!(0x8006, 0xF00F, "SHR Vx, Vy", Shr8xy6),
+    opcode!(0x8007, 0xF00F, "SUBN Vx, Vy", Subn8xy7),
+    opcode!(0x800E, 0xF00F, "SHL Vx, Vy", Shl8xyE),
+    opcode!(0x9000, 0xF00F, "SNE Vx, Vy", Sne9xy0),
+    opcode!(0xA000, 0xF000, "LD I, nnn", LdAnnn),
+    opcode!(0xB000, 0xF000, "JP V0, nnn", JpBnnn),
+    opcode!(0xC000, 0xF000, "RND Vx, kk", RndCxkk),
+    opcode!(0xD000, 0xF000, "DRW Vx, Vy, n", DrwDxyn),
+    opcode!(0xE09E, 0xF0FF, "SKP Vx", SkpEx9e),
+    opcode!(0xE0A1, 0xF0FF, "SKNP Vx", SknpExA1),
+    opcode!(0xF000, 0xFFFF, "LD I, LONG nnnn", LdF000),
+    opcode!(0xF001, 0xF0FF, "PLANE p", PlaneFn01),
+    opcode!(0xF002, 0xFFFF, "AUDIO", AudioF002),
+    opcode!(0xF007, 0xF0FF, "LD Vx, DT", LdFx07),
+    opcode!(0xF00A, 0xF0FF, "LD Vx, K", LdFx0a),
+    opcode!(0xF015, 0xF0FF, "LD DT, Vx", LdFx15),
+    opcode!(0xF018, 0xF0FF, "LD ST, Vx", LdFx18),
+    opcode!(0xF01E, 0xF0FF, "ADD I, Vx", AddFx1e),
+    opcode!(0xF029, 0xF0FF, "LD F, Vx", LdFx29),
+    opcode!(0xF030, 0xF0FF, "LD HF, Vx", LdFx30),
+    opcode!(0xF033, 0xF0FF, "LD B, Vx", LdFx33),
+    opcode!(0xF03A, 0xF0FF, "PITCH Vx", PitchFx3A),
+    opcode!(0xF055, 0xF0FF, "LD [I], Vx", LdFx55),
+    opcode!(0xF065, 0xF0FF, "LD Vx, [I]", LdFx65),
+    opcode!(0xF075, 0xF0FF, "LD R, Vx", LdFx75),
+    opcode!(0xF085, 0xF0FF, "LD Vx, R", LdFx85),
+];
+
+pub(crate) fn decode(opcode: u16) -> Option<&'static OpcodeDef> {
+    OPCODES.iter().find(|def| opcode & def.mask == def.pattern)
+}
+
+/// Names of the operand placeholders used in `OpcodeDef::syntax`.
+pub(crate) const PLACEHOLDERS: [&str; 7] = ["Vx", "Vy", "kk", "nnn", "n", "p", "nnnn"];
+
+/// Position of the opcode bits selected by an operand placeholder as (shift, mask). `nnnn` is not part of the opcode.
+fn layout(placeholder: &str) -> Option<(u32, u16)> {
+    match placeholder {
+        "Vx" | "p" => Some((8, 0xF)),
+        "Vy" => Some((4, 0xF)),
+        "kk" => Some((0, 0xFF)),
+        "nnn" => Some((0, 0xFFF)),
+        "n" => Some((0, 0xF)),
+        _ => None,
+    }
+}
+
+/// Returns the opcode bits selected by an operand placeholder.
+pub(crate) fn field(placeholder: &str, opcode: u16) -> Option<u16> {
+    layout(placeholder).map(|(shift, mask)| (opcode >> shift) & mask)
+}
+
+/// Stores `value` in the opcode bits selected by an operand placeholder, `value` must fit in `field_bits`.
+pub(crate) fn with_field(opcode: u16, placeholder: &str, value: u16) -> u16 {
+    match layout(placeholder) {
+        Some((shift, mask)) => opcode | (value & mask) << shift,
+        None => opcode,
+    }
+}
+
+/// Width of an operand placeholder in bits.
+pub(crate) fn field_bits(placeholder: &str) -> u32 {
+    layout(placeholder).map_or(16, |(_, mask)| mask.count_ones())
+}
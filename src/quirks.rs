@@ -0,0 +1,105 @@
+use std::ops::RangeInclusive;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexIncrement {
+    Unchanged,
+    ByX,
+    ByXPlusOne,
+}
+
+/// Behaviours of the instructions that differ between CHIP-8 platforms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    /// 8xy6/8xyE shift VY into VX instead of shifting VX in place.
+    pub shift_uses_vy: bool,
+    /// How Fx55/Fx65 leave I after the transfer.
+    pub load_store_index: IndexIncrement,
+    /// Bnnn jumps to nnn + VX (x being the high nibble of nnn) instead of nnn + V0.
+    pub jump_uses_vx: bool,
+    /// 8xy1/8xy2/8xy3 reset VF to 0.
+    pub logic_resets_vf: bool,
+    /// Sprites wrap around the screen edges instead of being clipped.
+    pub sprite_wrap: bool,
+    /// Dxyn waits for the next vblank before drawing.
+    pub display_wait: bool,
+    /// Size of the addressable memory in bytes, `Cpu::with_quirks` clamps it to `MEMORY_SIZES`.
+    pub memory_size: usize,
+}
+
+impl Quirks {
+    /// Supported memory sizes, from the 4 KiB of the original machines to the 64 KiB I can address in XO-CHIP.
+    pub const MEMORY_SIZES: RangeInclusive<usize> = 0x1000..=0x10000;
+
+    /// What most modern interpreters implement, and how this emulator behaved before quirks were configurable. Used
+    /// by default, since it doesn't need `Cpu::tick_timers` to be called for Dxyn to make progress.
+    pub const MODERN: Quirks = Quirks {
+        shift_uses_vy: false,
+        load_store_index: IndexIncrement::Unchanged,
+        jump_uses_vx: false,
+        logic_resets_vf: false,
+        sprite_wrap: false,
+        display_wait: false,
+        memory_size: 4096,
+    };
+
+    pub const COSMAC_VIP: Quirks = Quirks {
+        shift_uses_vy: true,
+        load_store_index: IndexIncrement::ByXPlusOne,
+        jump_uses_vx: false,
+        logic_resets_vf: true,
+        sprite_wrap: false,
+        display_wait: true,
+        memory_size: 4096,
+    };
+
+    pub const CHIP_48: Quirks = Quirks {
+        shift_uses_vy: false,
+        load_store_index: IndexIncrement::ByX,
+        jump_uses_vx: true,
+        logic_resets_vf: false,
+        sprite_wrap: false,
+        display_wait: false,
+        memory_size: 4096,
+    };
+
+    pub const SUPER_CHIP: Quirks = Quirks {
+        shift_uses_vy: false,
+        load_store_index: IndexIncrement::Unchanged,
+        jump_uses_vx: true,
+        logic_resets_vf: false,
+        sprite_wrap: false,
+        display_wait: false,
+        memory_size: 4096,
+    };
+
+    pub const XO_CHIP: Quirks = Quirks {
+        shift_uses_vy: true,
+        load_store_index: IndexIncrement::ByXPlusOne,
+        jump_uses_vx: false,
+        logic_resets_vf: false,
+        sprite_wrap: true,
+        display_wait: false,
+        memory_size: 65536,
+    };
+
+    pub const PRESETS: [(&'static str, Quirks); 5] = [
+        ("modern", Quirks::MODERN),
+        ("vip", Quirks::COSMAC_VIP),
+        ("chip48", Quirks::CHIP_48),
+        ("schip", Quirks::SUPER_CHIP),
+        ("xochip", Quirks::XO_CHIP),
+    ];
+
+    pub fn from_name(name: &str) -> Option<Quirks> {
+        Self::PRESETS
+            .iter()
+            .find(|(preset, _)| preset.eq_ignore_ascii_case(name))
+            .map(|(_, quirks)| *quirks)
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks::MODERN
+    }
+}
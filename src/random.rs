@@ -0,0 +1,44 @@
+/// Source of the random bytes used by Cxkk.
+pub trait RandomSource {
+    fn next_byte(&mut self) -> u8;
+
+    /// The complete generator state, so it can be included in snapshots of the machine.
+    fn state(&self) -> u64;
+
+    fn set_state(&mut self, state: u64);
+}
+
+/// Small xorshift64* generator, deterministic for a given seed.
+pub struct XorShiftRng {
+    state: u64,
+}
+
+impl XorShiftRng {
+    pub fn new(seed: u64) -> Self {
+        // Run the seed through splitmix64 so similar seeds give unrelated sequences, xorshift needs a non zero state
+        let mut z = seed.wrapping_add(0x9E3779B97F4A7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^= z >> 31;
+        Self { state: if z == 0 { 1 } else { z } }
+    }
+}
+
+impl RandomSource for XorShiftRng {
+    fn next_byte(&mut self) -> u8 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        (x.wrapping_mul(0x2545F4914F6CDD1D) >> 56) as u8
+    }
+
+    fn state(&self) -> u64 {
+        self.state
+    }
+
+    fn set_state(&mut self, state: u64) {
+        self.state = state;
+    }
+}
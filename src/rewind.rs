@@ -0,0 +1,146 @@
+use std::collections::VecDeque;
+
+use crate::Cpu;
+
+const FRAMES_PER_SECOND: usize = 60;
+
+enum Entry {
+    /// Run length encoded XOR of the next newer state against this one.
+    Delta(Vec<u8>),
+    /// Complete state, kept when the state size changed between frames (e.g. a resolution switch).
+    Full(Vec<u8>),
+}
+
+/// Ring buffer of per-frame save states. Only the newest state is kept in full, every older frame is stored as the
+/// compressed difference to the frame after it.
+pub struct Rewind {
+    capacity: usize,
+    entries: VecDeque<Entry>,
+    current: Option<Vec<u8>>,
+}
+
+impl Rewind {
+    pub fn new(seconds: usize) -> Self {
+        Self {
+            capacity: seconds * FRAMES_PER_SECOND,
+            entries: VecDeque::new(),
+            current: None,
+        }
+    }
+
+    /// Records the state of `cpu`, should be called once per frame.
+    pub fn push(&mut self, cpu: &Cpu) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let state = cpu.save_state();
+        if let Some(previous) = self.current.take() {
+            let entry = if previous.len() == state.len() {
+                Entry::Delta(compress(&xor(&previous, &state)))
+            } else {
+                Entry::Full(previous)
+            };
+            if self.entries.len() == self.capacity {
+                self.entries.pop_front();
+            }
+            self.entries.push_back(entry);
+        }
+        self.current = Some(state);
+    }
+
+    /// Restores `cpu` to the frame before the last restored or recorded one. Returns false once the history is
+    /// exhausted.
+    pub fn rewind(&mut self, cpu: &mut Cpu) -> bool {
+        // Checked first so the newest state is kept, frames pushed afterwards are still stored relative to it
+        if self.entries.is_empty() {
+            return false;
+        }
+        let (Some(entry), Some(current)) = (self.entries.pop_back(), self.current.take()) else {
+            return false;
+        };
+
+        let previous = match entry {
+            Entry::Delta(delta) => xor(&current, &decompress(&delta, current.len())),
+            Entry::Full(state) => state,
+        };
+        let restored = cpu.load_state(&previous).is_ok();
+        self.current = Some(previous);
+        restored
+    }
+
+    /// Number of frames that can be rewound.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.current = None;
+    }
+}
+
+fn xor(lhs: &[u8], rhs: &[u8]) -> Vec<u8> {
+    lhs.iter().zip(rhs).map(|(a, b)| a ^ b).collect()
+}
+
+/// Encodes the delta as pairs of (zero run length, literal length, literal bytes) using LEB128 lengths. Most of the
+/// machine does not change between frames, so the deltas are almost entirely zeros.
+fn compress(delta: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < delta.len() {
+        let zeros = delta[i..].iter().take_while(|&&b| b == 0).count();
+        i += zeros;
+        let literals = delta[i..].iter().take_while(|&&b| b != 0).count();
+        write_length(&mut out, zeros);
+        write_length(&mut out, literals);
+        out.extend_from_slice(&delta[i..i + literals]);
+        i += literals;
+    }
+    out
+}
+
+fn decompress(data: &[u8], len: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(len);
+    let mut i = 0;
+    while i < data.len() {
+        let zeros = read_length(data, &mut i);
+        let literals = read_length(data, &mut i);
+        out.resize(out.len() + zeros, 0);
+        out.extend_from_slice(&data[i..i + literals]);
+        i += literals;
+    }
+    out.resize(len, 0);
+    out
+}
+
+fn write_length(out: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn read_length(data: &[u8], i: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = data[*i];
+        *i += 1;
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
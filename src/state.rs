@@ -0,0 +1,194 @@
+use crate::cpu::KeyWait;
+use crate::{Cpu, StateError, HIRES_VIDEO_HEIGHT, HIRES_VIDEO_WIDTH, VIDEO_HEIGHT, VIDEO_WIDTH};
+
+const MAGIC: &[u8; 4] = b"C8ST";
+const VERSION: u16 = 1;
+
+impl Cpu {
+    /// Serializes the complete machine state, including the random number generator, into a versioned binary format.
+    /// The quirks are not part of the state, they have to match when the state is loaded again.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.memory.len() + self.video.len() + 256);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+
+        out.extend_from_slice(&self.registers);
+        out.extend_from_slice(&self.index.to_le_bytes());
+        out.extend_from_slice(&self.program_counter.to_le_bytes());
+        for address in self.stack {
+            out.extend_from_slice(&address.to_le_bytes());
+        }
+        out.extend_from_slice(&self.stack_pointer.to_le_bytes());
+        out.push(self.delay_timer);
+        out.push(self.sound_timer);
+        out.extend_from_slice(&self.keypad);
+
+        out.push(self.hires as u8);
+        out.push(self.selected_planes);
+        out.extend_from_slice(&self.video);
+
+        out.extend_from_slice(&self.rpl_flags);
+        out.push(self.audio_pattern.is_some() as u8);
+        out.extend_from_slice(&self.audio_pattern.unwrap_or_default());
+        out.push(self.pitch);
+        out.push(self.exited as u8);
+        out.push(self.vblank as u8);
+
+        match &self.key_wait {
+            Some(wait) => {
+                out.push(1);
+                out.extend_from_slice(&wait.previous_keypad);
+                out.push(wait.pressed.map_or(0xFF, |key| key));
+            }
+            None => {
+                out.push(0);
+                out.extend_from_slice(&[0; 16]);
+                out.push(0xFF);
+            }
+        }
+
+        out.extend_from_slice(&self.rng.state().to_le_bytes());
+        out.extend_from_slice(&self.seed.to_le_bytes());
+
+        out.extend_from_slice(&(self.memory.len() as u32).to_le_bytes());
+        out.extend_from_slice(&self.memory);
+        out
+    }
+
+    /// Restores a state produced by `save_state`. The cpu is left untouched if the state is invalid.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        let mut reader = StateReader::new(data, || StateError::Truncated);
+        if reader.bytes(MAGIC.len())? != MAGIC {
+            return Err(StateError::InvalidMagic);
+        }
+        let version = reader.u16()?;
+        if version != VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+
+        let registers = reader.array()?;
+        let index = reader.u16()?;
+        let program_counter = reader.u16()?;
+        let mut stack = [0; 16];
+        for address in stack.iter_mut() {
+            *address = reader.u16()?;
+        }
+        let stack_pointer = reader.u16()?;
+        if stack_pointer as usize > stack.len() {
+            return Err(StateError::InvalidData("stack pointer"));
+        }
+        let delay_timer = reader.u8()?;
+        let sound_timer = reader.u8()?;
+        let keypad = reader.array()?;
+
+        let hires = reader.bool()?;
+        let selected_planes = reader.u8()?;
+        let video_size = if hires {
+            HIRES_VIDEO_WIDTH * HIRES_VIDEO_HEIGHT
+        } else {
+            VIDEO_WIDTH * VIDEO_HEIGHT
+        };
+        let video = reader.bytes(video_size)?.to_vec();
+
+        let rpl_flags = reader.array()?;
+        let has_audio_pattern = reader.bool()?;
+        let audio_pattern: [u8; 16] = reader.array()?;
+        let pitch = reader.u8()?;
+        let exited = reader.bool()?;
+        let vblank = reader.bool()?;
+
+        let waiting_for_key = reader.bool()?;
+        let previous_keypad = reader.array()?;
+        let pressed = match reader.u8()? {
+            0xFF => None,
+            key if (key as usize) < keypad.len() => Some(key),
+            _ => return Err(StateError::InvalidData("pressed key")),
+        };
+
+        let rng_state = reader.u64()?;
+        let seed = reader.u64()?;
+
+        let memory_size = reader.u32()? as usize;
+        if memory_size != self.memory.len() {
+            return Err(StateError::MemorySizeMismatch {
+                expected: self.memory.len(),
+                found: memory_size,
+            });
+        }
+        let memory = reader.bytes(memory_size)?;
+
+        self.registers = registers;
+        self.index = index;
+        self.program_counter = program_counter;
+        self.stack = stack;
+        self.stack_pointer = stack_pointer;
+        self.delay_timer = delay_timer;
+        self.sound_timer = sound_timer;
+        self.keypad = keypad;
+        self.hires = hires;
+        self.selected_planes = selected_planes;
+        self.video = video;
+        self.display_changed = true;
+        self.rpl_flags = rpl_flags;
+        self.audio_pattern = has_audio_pattern.then_some(audio_pattern);
+        self.pitch = pitch;
+        self.exited = exited;
+        self.vblank = vblank;
+        self.key_wait = waiting_for_key.then_some(KeyWait { previous_keypad, pressed });
+        self.rng.set_state(rng_state);
+        self.seed = seed;
+        self.memory.copy_from_slice(memory);
+        Ok(())
+    }
+}
+
+/// Reads the little endian fields of the versioned binary formats, failing with the `truncated` error when the data
+/// ends early.
+pub(crate) struct StateReader<'a, E> {
+    data: &'a [u8],
+    position: usize,
+    truncated: fn() -> E,
+}
+
+impl<'a, E> StateReader<'a, E> {
+    pub(crate) fn new(data: &'a [u8], truncated: fn() -> E) -> Self {
+        Self {
+            data,
+            position: 0,
+            truncated,
+        }
+    }
+
+    pub(crate) fn bytes(&mut self, len: usize) -> Result<&'a [u8], E> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + len)
+            .ok_or_else(self.truncated)?;
+        self.position += len;
+        Ok(bytes)
+    }
+
+    pub(crate) fn array<const N: usize>(&mut self) -> Result<[u8; N], E> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, E> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub(crate) fn bool(&mut self) -> Result<bool, E> {
+        Ok(self.u8()? != 0)
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, E> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, E> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, E> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+
+use crate::{disassemble_at, Cpu, CpuError, Instruction, Register, StepInfo};
+
+/// Registers compared before and after every instruction. The PC is left out as it changes every time.
+const TRACED_REGISTERS: [Register; 20] = [
+    Register::V(0x0),
+    Register::V(0x1),
+    Register::V(0x2),
+    Register::V(0x3),
+    Register::V(0x4),
+    Register::V(0x5),
+    Register::V(0x6),
+    Register::V(0x7),
+    Register::V(0x8),
+    Register::V(0x9),
+    Register::V(0xA),
+    Register::V(0xB),
+    Register::V(0xC),
+    Register::V(0xD),
+    Register::V(0xE),
+    Register::V(0xF),
+    Register::I,
+    Register::Sp,
+    Register::Dt,
+    Register::St,
+];
+
+/// State taken before an instruction executes, so self-modifying code is traced as the instruction that ran.
+pub(crate) struct Snapshot {
+    registers: [u16; TRACED_REGISTERS.len()],
+    instruction: Option<Instruction>,
+}
+
+/// One executed instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub cycle: u64,
+    pub program_counter: u16,
+    pub opcode: u16,
+    /// Text of the instruction, e.g. `LD V3, 0x1F`.
+    pub instruction: String,
+    /// Registers changed by the instruction as (register, new value).
+    pub changes: Vec<(Register, u16)>,
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:>10}  {:03X}  {:04X}  ", self.cycle, self.program_counter, self.opcode)?;
+        if self.changes.is_empty() {
+            return write!(f, "{}", self.instruction);
+        }
+        write!(f, "{:<20}", self.instruction)?;
+        for (register, value) in &self.changes {
+            write!(f, "  {}={:02X}", register, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes one line per executed instruction, attached to a cpu with `Cpu::set_trace`.
+///
+/// Only instructions in the address range are written. In ring buffer mode nothing is written until a `CpuError`
+/// occurs, then the last instructions before the error are dumped.
+pub struct Trace {
+    writer: Box<dyn Write>,
+    range: RangeInclusive<u16>,
+    ring: Option<(VecDeque<TraceEntry>, usize)>,
+    cycle: u64,
+    error: Option<io::Error>,
+}
+
+impl Trace {
+    pub fn new<W: Write + 'static>(writer: W) -> Self {
+        Self {
+            writer: Box::new(writer),
+            range: 0..=u16::MAX,
+            ring: None,
+            cycle: 0,
+            error: None,
+        }
+    }
+
+    /// Limits the trace to instructions at addresses within `range`.
+    pub fn set_address_range(&mut self, range: RangeInclusive<u16>) {
+        self.range = range;
+    }
+
+    /// Keeps the last `capacity` instructions in memory and only writes them when a `CpuError` occurs.
+    pub fn set_ring_buffer(&mut self, capacity: usize) {
+        self.ring = Some((VecDeque::with_capacity(capacity), capacity));
+    }
+
+    /// Flushes the trace, returning the first error that occurred while writing it.
+    pub fn finish(mut self) -> io::Result<()> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.writer.flush()
+    }
+
+    pub(crate) fn snapshot(cpu: &Cpu) -> Snapshot {
+        Snapshot {
+            registers: TRACED_REGISTERS.map(|register| cpu.register(register)),
+            instruction: disassemble_at(cpu.memory(), cpu.program_counter() as usize),
+        }
+    }
+
+    pub(crate) fn record(&mut self, cpu: &Cpu, before: Snapshot, step: StepInfo) {
+        self.cycle += 1;
+        if !self.range.contains(&step.program_counter) {
+            return;
+        }
+
+        let instruction = before
+            .instruction
+            .map_or_else(|| format!("DW {:#06X}", step.opcode), |instruction| instruction.to_string());
+        let changes = TRACED_REGISTERS
+            .iter()
+            .zip(before.registers)
+            .filter(|&(&register, value)| cpu.register(register) != value)
+            .map(|(&register, _)| (register, cpu.register(register)))
+            .collect();
+        let entry = TraceEntry {
+            cycle: self.cycle,
+            program_counter: step.program_counter,
+            opcode: step.opcode,
+            instruction,
+            changes,
+        };
+
+        match &mut self.ring {
+            Some((entries, capacity)) => {
+                if entries.len() == *capacity {
+                    entries.pop_front();
+                }
+                if *capacity > 0 {
+                    entries.push_back(entry);
+                }
+            }
+            None => self.write_line(&entry),
+        }
+    }
+
+    pub(crate) fn record_error(&mut self, error: &CpuError) {
+        self.cycle += 1;
+        if let Some((entries, _)) = &mut self.ring {
+            let entries: Vec<TraceEntry> = entries.drain(..).collect();
+            self.write_line(&format!("Last {} instructions before the error:", entries.len()));
+            for entry in &entries {
+                self.write_line(entry);
+            }
+        }
+        self.write_line(&format!("{:>10}  {}", self.cycle, error));
+    }
+
+    fn write_line(&mut self, line: &dyn fmt::Display) {
+        if self.error.is_none() {
+            if let Err(e) = writeln!(self.writer, "{}", line) {
+                self.error = Some(e);
+            }
+        }
+    }
+}
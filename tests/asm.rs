@@ -0,0 +1,107 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chip8::{assemble, disassemble_rom, AsmError, AsmErrorKind};
+
+fn assemble_str(source: &str) -> Result<Vec<u8>, AsmError> {
+    assemble(source, Path::new("test.asm"))
+}
+
+/// Writes include test files into a directory of their own, removed again when dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> TempDir {
+        let path = std::env::temp_dir().join(format!("chip8-asm-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    fn write(&self, name: &str, source: &str) -> PathBuf {
+        let path = self.0.join(name);
+        fs::write(&path, source).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn disassembled_games_assemble_to_the_same_bytes() {
+    for name in ["breakout", "tetris", "test_opcode"] {
+        let rom = fs::read(format!("games/{}.ch8", name)).unwrap();
+        // Drop the address and raw bytes columns of the listing, leaving the assembly
+        let source: Vec<String> = disassemble_rom(&rom)
+            .iter()
+            .map(|line| line.to_string().splitn(3, "  ").nth(2).unwrap().trim().to_string())
+            .collect();
+        let assembled = assemble_str(&source.join("\n")).unwrap_or_else(|e| panic!("{}: {}", name, e));
+        assert!(assembled == rom, "{} doesn't round-trip", name);
+    }
+}
+
+#[test]
+fn labels_can_be_used_before_they_are_defined() {
+    let rom = assemble_str("JP end\nLD I, data\nend: RET\ndata: db 1, 2").unwrap();
+    assert_eq!(rom, [0x12, 0x04, 0xA2, 0x06, 0x00, 0xEE, 0x01, 0x02]);
+}
+
+#[test]
+fn fixed_operands_are_not_read_as_values() {
+    assert_eq!(assemble_str("LD V3, K").unwrap(), [0xF3, 0x0A]);
+    assert_eq!(assemble_str("LD V3, 0x0A").unwrap(), [0x63, 0x0A]);
+    assert_eq!(assemble_str("LD I, LONG 0x1234").unwrap(), [0xF0, 0x00, 0x12, 0x34]);
+    assert_eq!(assemble_str("LD I, 0x234").unwrap(), [0xA2, 0x34]);
+    assert!(matches!(assemble_str("K = 5").unwrap_err().kind, AsmErrorKind::ReservedName(_)));
+}
+
+#[test]
+fn recursive_constants_are_rejected() {
+    let error = assemble_str("FOO = BAR + 1\nBAR = FOO\nLD V0, FOO").unwrap_err();
+    assert!(matches!(error.kind, AsmErrorKind::RecursiveConstant(_)), "{}", error);
+    assert_eq!(error.line, 3);
+}
+
+#[test]
+fn recursive_includes_are_rejected() {
+    let dir = TempDir::new("recursive");
+    dir.write("a.asm", "CLS\ninclude \"b.asm\"");
+    dir.write("b.asm", "RET\n\ninclude \"a.asm\"");
+    let path = dir.0.join("a.asm");
+    let error = assemble(&fs::read_to_string(&path).unwrap(), &path).unwrap_err();
+    assert!(matches!(error.kind, AsmErrorKind::RecursiveInclude(_)), "{}", error);
+    assert!(error.file.ends_with("b.asm"), "{}", error);
+    assert_eq!(error.line, 3);
+}
+
+#[test]
+fn included_files_share_labels_and_constants() {
+    let dir = TempDir::new("include");
+    dir.write("sprites.asm", "SIZE = 2\nsprite: db 0xFF, 0x81");
+    let path = dir.write("main.asm", "LD I, sprite\nDRW V0, V1, SIZE\ninclude \"sprites.asm\"");
+    let rom = assemble(&fs::read_to_string(&path).unwrap(), &path).unwrap();
+    assert_eq!(rom, [0xA2, 0x04, 0xD0, 0x12, 0xFF, 0x81]);
+}
+
+#[test]
+fn errors_report_the_file_and_line() {
+    let error = assemble_str("CLS\n; comment\n\nJP nowhere").unwrap_err();
+    assert!(matches!(error.kind, AsmErrorKind::UndefinedSymbol(_)));
+    assert_eq!(error.to_string(), "test.asm:4: undefined symbol 'nowhere'");
+
+    let error = assemble_str("CLS\nLD V0, 0x100").unwrap_err();
+    assert!(matches!(error.kind, AsmErrorKind::ValueOutOfRange { value: 0x100, bits: 8 }));
+    assert_eq!(error.line, 2);
+
+    let error = assemble_str("start:\nstart: RET").unwrap_err();
+    assert!(matches!(error.kind, AsmErrorKind::DuplicateSymbol(_)));
+    assert_eq!(error.line, 2);
+
+    let error = assemble_str("CLS\nJUMP 0x200").unwrap_err();
+    assert!(matches!(error.kind, AsmErrorKind::UnknownInstruction(_)));
+    assert_eq!(error.line, 2);
+}
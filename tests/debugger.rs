@@ -0,0 +1,147 @@
+use chip8::{Cpu, Debugger, MemoryAccess, MemoryHook, Quirks, Register, StopReason, Watchpoint};
+
+fn load(quirks: Quirks, rom: &[u8]) -> Cpu {
+    let mut cpu = Cpu::with_quirks(quirks);
+    cpu.load_rom(rom).unwrap();
+    cpu
+}
+
+fn step(debugger: &mut Debugger, cpu: &mut Cpu, instructions_per_frame: usize) -> Option<StopReason> {
+    debugger.step();
+    debugger.run_frame(cpu, instructions_per_frame)
+}
+
+#[test]
+fn stepping_gets_past_a_draw_waiting_for_the_vblank() {
+    // DRW V0, V1, 5; DRW V0, V1, 5; JP 0x204
+    let mut cpu = load(Quirks::COSMAC_VIP, &[0xD0, 0x15, 0xD0, 0x15, 0x12, 0x04]);
+    let mut debugger = Debugger::new();
+
+    // The first attempt waits for the vblank, which ends the frame
+    assert_eq!(step(&mut debugger, &mut cpu, 10), Some(StopReason::Step));
+    assert_eq!(cpu.program_counter(), 0x200);
+    assert_eq!(step(&mut debugger, &mut cpu, 10), Some(StopReason::Step));
+    assert_eq!(cpu.program_counter(), 0x202);
+    assert_eq!(step(&mut debugger, &mut cpu, 10), Some(StopReason::Step));
+    assert_eq!(step(&mut debugger, &mut cpu, 10), Some(StopReason::Step));
+    assert_eq!(cpu.program_counter(), 0x204);
+}
+
+#[test]
+fn timers_count_down_while_stepping() {
+    // LD V0, 0x05; LD DT, V0; LD V1, 0x01; LD V2, 0x02; JP 0x208
+    let mut cpu = load(Quirks::MODERN, &[0x60, 0x05, 0xF0, 0x15, 0x61, 0x01, 0x62, 0x02, 0x12, 0x08]);
+    let mut debugger = Debugger::new();
+
+    step(&mut debugger, &mut cpu, 3);
+    step(&mut debugger, &mut cpu, 3);
+    assert_eq!(cpu.delay_timer(), 5);
+    // The third instruction completes the frame
+    step(&mut debugger, &mut cpu, 3);
+    assert_eq!(cpu.delay_timer(), 4);
+    step(&mut debugger, &mut cpu, 3);
+    assert_eq!(cpu.delay_timer(), 4);
+    // Jumping to itself doesn't advance the PC, which ends the frame early
+    step(&mut debugger, &mut cpu, 3);
+    assert_eq!(cpu.delay_timer(), 3);
+
+    // Running continues the frame the steps started
+    debugger.resume();
+    assert_eq!(debugger.run_frame(&mut cpu, 3), None);
+    assert_eq!(cpu.delay_timer(), 2);
+}
+
+/// Records every memory access reported by the cpu.
+#[derive(Default)]
+struct Accesses(Vec<(usize, MemoryAccess, u8)>);
+
+impl MemoryHook for Accesses {
+    fn access(&mut self, address: usize, access: MemoryAccess, value: u8) {
+        self.0.push((address, access, value));
+    }
+}
+
+#[test]
+fn memory_accesses_are_reported_to_the_hook() {
+    // LD I, 0x300; LD V0, 0x07; LD B, V0; LD V1, [I]; LD I, 0x050; DRW V0, V0, 2
+    let rom = [0xA3, 0x00, 0x60, 0x07, 0xF0, 0x33, 0xF1, 0x65, 0xA0, 0x50, 0xD0, 0x02];
+    let mut cpu = load(Quirks::MODERN, &rom);
+    let mut accesses = Accesses::default();
+    for _ in 0..6 {
+        cpu.cycle_with_hook(&mut accesses).unwrap();
+    }
+    assert_eq!(
+        accesses.0,
+        [
+            // BCD digits are written from the last one
+            (0x302, MemoryAccess::Write, 7),
+            (0x301, MemoryAccess::Write, 0),
+            (0x300, MemoryAccess::Write, 0),
+            (0x300, MemoryAccess::Read, 0),
+            (0x301, MemoryAccess::Read, 0),
+            // Sprite rows of the font's 0
+            (0x050, MemoryAccess::Read, 0xF0),
+            (0x051, MemoryAccess::Read, 0x90),
+        ]
+    );
+}
+
+#[test]
+fn memory_watchpoints_pause_on_matching_accesses() {
+    // LD I, 0x300; LD V0, [I]; LD [I], V0; JP 0x204
+    let rom = [0xA3, 0x00, 0xF0, 0x65, 0xF0, 0x55, 0x12, 0x04];
+    let mut cpu = load(Quirks::MODERN, &rom);
+    let mut debugger = Debugger::new();
+    let watchpoint = Watchpoint::Memory { start: 0x2FF, end: 0x300, access: Some(MemoryAccess::Write) };
+    assert!(debugger.add_watchpoint(watchpoint));
+
+    debugger.resume();
+    let reason = debugger.run_frame(&mut cpu, 10);
+    assert_eq!(reason, Some(StopReason::MemoryWatch { address: 0x300, access: MemoryAccess::Write, value: 0 }));
+    assert_eq!(cpu.program_counter(), 0x206);
+
+    // Reads only
+    let mut cpu = load(Quirks::MODERN, &rom);
+    let mut debugger = Debugger::new();
+    debugger.add_watchpoint(Watchpoint::Memory { start: 0x300, end: 0x300, access: Some(MemoryAccess::Read) });
+    debugger.resume();
+    let reason = debugger.run_frame(&mut cpu, 10);
+    assert_eq!(reason, Some(StopReason::MemoryWatch { address: 0x300, access: MemoryAccess::Read, value: 0 }));
+    assert_eq!(cpu.program_counter(), 0x204);
+
+    // Outside of the range nothing triggers
+    let mut cpu = load(Quirks::MODERN, &rom);
+    let mut debugger = Debugger::new();
+    debugger.add_watchpoint(Watchpoint::Memory { start: 0x301, end: 0x310, access: None });
+    debugger.resume();
+    assert_eq!(debugger.run_frame(&mut cpu, 10), None);
+}
+
+#[test]
+fn register_watchpoints_pause_when_the_value_changes() {
+    // LD V3, 0x00; LD V3, 0x05; LD I, 0x300; JP 0x206
+    let rom = [0x63, 0x00, 0x63, 0x05, 0xA3, 0x00, 0x12, 0x06];
+    let mut cpu = load(Quirks::MODERN, &rom);
+    let mut debugger = Debugger::new();
+    debugger.add_watchpoint(Watchpoint::Register(Register::V(3)));
+    debugger.add_watchpoint(Watchpoint::Register(Register::I));
+
+    // Writing the value it already has doesn't count as a change
+    debugger.resume();
+    let reason = debugger.run_frame(&mut cpu, 10);
+    assert_eq!(reason, Some(StopReason::RegisterWatch { register: Register::V(3), old: 0, new: 5 }));
+    assert_eq!(cpu.program_counter(), 0x204);
+
+    debugger.resume();
+    let reason = debugger.run_frame(&mut cpu, 10);
+    assert_eq!(reason, Some(StopReason::RegisterWatch { register: Register::I, old: 0, new: 0x300 }));
+    debugger.resume();
+    assert_eq!(debugger.run_frame(&mut cpu, 10), None);
+}
+
+#[test]
+fn pc_can_not_be_watched() {
+    let mut debugger = Debugger::new();
+    assert!(!debugger.add_watchpoint(Watchpoint::Register(Register::Pc)));
+    assert!(debugger.watchpoints().is_empty());
+}
@@ -0,0 +1,92 @@
+use chip8::{disassemble, disassemble_at, disassemble_rom, ListingLine};
+
+/// Summarizes a listing as (address, text) with `DB` for data lines, e.g. (0x202, "DB 0xAB, 0xCD").
+fn listing(rom: &[u8]) -> Vec<(u16, String)> {
+    disassemble_rom(rom)
+        .into_iter()
+        .map(|line| match line {
+            ListingLine::Code { address, instruction } => (address, instruction.to_string()),
+            ListingLine::Data { address, bytes } => {
+                let values: Vec<String> = bytes.iter().map(|b| format!("{:#04X}", b)).collect();
+                (address, format!("DB {}", values.join(", ")))
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn instructions_format_as_assembly() {
+    assert_eq!(disassemble(0x631F).to_string(), "LD V3, 0x1F");
+    assert_eq!(disassemble(0xD015).to_string(), "DRW V0, V1, 5");
+    assert_eq!(disassemble(0xA30C).to_string(), "LD I, 0x30C");
+    assert_eq!(disassemble(0x2ABC).to_string(), "CALL 0xABC");
+    assert_eq!(disassemble(0xF40A).to_string(), "LD V4, K");
+    assert_eq!(disassemble(0x8AB6).to_string(), "SHR VA, VB");
+    assert_eq!(disassemble(0x00C3).to_string(), "SCD 3");
+    assert_eq!(disassemble(0xD120).to_string(), "DRW V1, V2, 0");
+    assert_eq!(disassemble(0x00E0).mnemonic(), "CLS");
+}
+
+#[test]
+fn long_load_includes_its_operand() {
+    let instruction = disassemble_at(&[0xF0, 0x00, 0x12, 0x34], 0).unwrap();
+    assert_eq!(instruction.size(), 4);
+    assert_eq!(instruction.to_string(), "LD I, LONG 0x1234");
+    assert_eq!(disassemble(0xF000).to_string(), "LD I, LONG ?");
+    assert_eq!(disassemble_at(&[0x12], 0), None);
+}
+
+#[test]
+fn unknown_opcodes_are_marked() {
+    let instruction = disassemble(0x5001);
+    assert!(!instruction.is_known());
+    assert_eq!(instruction.mnemonic(), "DW");
+    assert_eq!(instruction.to_string(), "DW 0x5001");
+
+    // Control flow stops at an unknown opcode, the bytes after it are data
+    let lines = disassemble_rom(&[0x50, 0x01, 0x00, 0xE0]);
+    assert_eq!(lines[0].to_string(), "200  5001       DW 0x5001  ; unknown opcode");
+    assert_eq!(lines[1].to_string(), "202  00E0       DB 0x00, 0xE0");
+}
+
+#[test]
+fn unreachable_bytes_after_jumps_and_returns_are_data() {
+    // JP 0x204; data; CALL 0x20A; EXIT; data; RET
+    let rom = [0x12, 0x04, 0xAB, 0xCD, 0x22, 0x0A, 0x00, 0xFD, 0x01, 0x02, 0x00, 0xEE, 0x03];
+    assert_eq!(
+        listing(&rom),
+        [
+            (0x200, "JP 0x204".to_string()),
+            (0x202, "DB 0xAB, 0xCD".to_string()),
+            (0x204, "CALL 0x20A".to_string()),
+            (0x206, "EXIT".to_string()),
+            (0x208, "DB 0x01, 0x02".to_string()),
+            (0x20A, "RET".to_string()),
+            (0x20C, "DB 0x03".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn skips_step_over_long_loads() {
+    // SE V0, 0x00; LD I, LONG 0x1234; EXIT; SKP V1; LD V0, 0x01; JP 0x20C
+    let rom = [0x30, 0x00, 0xF0, 0x00, 0x12, 0x34, 0xE1, 0x9E, 0x60, 0x01, 0x12, 0x0C];
+    assert_eq!(
+        listing(&rom),
+        [
+            (0x200, "SE V0, 0x00".to_string()),
+            (0x202, "LD I, LONG 0x1234".to_string()),
+            (0x206, "SKP V1".to_string()),
+            (0x208, "LD V0, 0x01".to_string()),
+            (0x20A, "JP 0x20C".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn long_data_is_split_into_lines() {
+    // JP 0x200 followed by 6 bytes of data
+    let lines = listing(&[0x12, 0x00, 1, 2, 3, 4, 5, 6]);
+    assert_eq!(lines[1], (0x202, "DB 0x01, 0x02, 0x03, 0x04".to_string()));
+    assert_eq!(lines[2], (0x206, "DB 0x05, 0x06".to_string()));
+}
@@ -0,0 +1,126 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use chip8::{Cpu, Debugger, GdbStub};
+
+/// LD V0, 0x05; ADD V0, 0x01; JP 0x204
+const ROM: [u8; 6] = [0x60, 0x05, 0x70, 0x01, 0x12, 0x04];
+
+struct Session {
+    stub: GdbStub,
+    cpu: Cpu,
+    debugger: Debugger,
+    stream: TcpStream,
+    received: Vec<u8>,
+}
+
+impl Session {
+    fn connect() -> Session {
+        let stub = GdbStub::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(stub.local_addr().unwrap()).unwrap();
+        stream.set_read_timeout(Some(Duration::from_millis(5))).unwrap();
+        let mut debugger = Debugger::new();
+        debugger.resume();
+        let mut session = Session { stub, cpu: Cpu::from_rom(&ROM).unwrap(), debugger, stream, received: Vec::new() };
+        session.run(|session| session.stub.connected());
+        assert!(session.debugger.paused());
+        session
+    }
+
+    /// Polls the stub and runs the debugger like the frontend does once per frame, until `done` returns true.
+    fn run(&mut self, mut done: impl FnMut(&mut Session) -> bool) {
+        for _ in 0..200 {
+            self.stub.poll(&mut self.cpu, &mut self.debugger).unwrap();
+            if let Some(reason) = self.debugger.run_frame(&mut self.cpu, 10) {
+                self.stub.report_stop(reason).unwrap();
+            }
+            let mut chunk = [0; 1024];
+            match self.stream.read(&mut chunk) {
+                Ok(read) => self.received.extend_from_slice(&chunk[..read]),
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(e) => panic!("{}", e),
+            }
+            if done(self) {
+                return;
+            }
+        }
+        panic!("No reply, received {:?}", String::from_utf8_lossy(&self.received));
+    }
+
+    fn send_raw(&mut self, data: &str) {
+        self.stream.write_all(data.as_bytes()).unwrap();
+    }
+
+    /// Sends a packet and returns the acknowledgement followed by the reply packet's data.
+    fn request(&mut self, data: &str) -> String {
+        self.send_raw(&format!("${}#{:02x}", data, checksum(data)));
+        self.run(|session| reply(&session.received).is_some());
+        let (reply, len) = reply(&self.received).unwrap();
+        self.received.drain(..len);
+        self.send_raw("+");
+        reply
+    }
+}
+
+fn checksum(data: &str) -> u8 {
+    data.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte))
+}
+
+/// Parses `+$data#xx` at the start of `received`, checking the checksum.
+fn reply(received: &[u8]) -> Option<(String, usize)> {
+    let text = std::str::from_utf8(received).ok()?;
+    let end = text.find('#')?;
+    if text.len() < end + 3 {
+        return None;
+    }
+    let (ack, data) = text[..end].split_once('$')?;
+    assert_eq!(u8::from_str_radix(&text[end + 1..end + 3], 16).unwrap(), checksum(data));
+    Some((format!("{}{}", ack, data), end + 3))
+}
+
+#[test]
+fn stop_reason_registers_and_memory() {
+    let mut session = Session::connect();
+    assert_eq!(session.request("?"), "+S05");
+    // V0-VF, then I and PC little endian, then SP, DT and ST
+    assert_eq!(session.request("g"), format!("+{}00000002000000", "00".repeat(16)));
+    assert_eq!(session.request("m200,6"), "+600570011204");
+    assert_eq!(session.request("M300,2:abcd"), "+OK");
+    assert_eq!(session.request("m300,2"), "+abcd");
+    assert_eq!(session.request("m1000,1"), "+E01");
+}
+
+#[test]
+fn breakpoint_continue_and_step() {
+    let mut session = Session::connect();
+    assert_eq!(session.request("Z0,202,2"), "+OK");
+    assert_eq!(session.request("c"), "+S05");
+    assert_eq!(session.cpu.program_counter(), 0x202);
+    assert_eq!(session.request("p0"), "+05");
+
+    assert_eq!(session.request("s"), "+S05");
+    assert_eq!(session.cpu.program_counter(), 0x204);
+    assert_eq!(session.request("p0"), "+06");
+    assert_eq!(session.request("p11"), "+0402");
+
+    assert_eq!(session.request("z0,202,2"), "+OK");
+    assert!(session.debugger.breakpoints().is_empty());
+}
+
+#[test]
+fn overflowing_watchpoint_is_rejected() {
+    let mut session = Session::connect();
+    assert_eq!(session.request("Z2,ffffffffffffffff,2"), "+E01");
+    assert!(session.debugger.watchpoints().is_empty());
+}
+
+#[test]
+fn bad_checksum_is_not_acknowledged() {
+    let mut session = Session::connect();
+    session.send_raw("$g#00");
+    session.run(|session| !session.received.is_empty());
+    assert_eq!(session.received, b"-");
+    session.received.clear();
+    assert_eq!(session.request("?"), "+S05");
+}
@@ -0,0 +1,107 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use chip8::{Cpu, Trace};
+
+/// ADD V0, 0x01; LD V1, K; JP 0x204
+const ROM: [u8; 6] = [0x70, 0x01, 0xF1, 0x0A, 0x12, 0x04];
+
+/// Collects the trace in memory to count the executed instructions.
+#[derive(Clone, Default)]
+struct Lines(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Lines {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Lines {
+    fn take(&self) -> Vec<String> {
+        let text = String::from_utf8(self.0.borrow_mut().split_off(0)).unwrap();
+        text.lines().map(str::to_string).collect()
+    }
+}
+
+/// Runs until the cpu waits on Fx0A.
+fn waiting_cpu(held_key: Option<u8>) -> Cpu {
+    let mut cpu = Cpu::from_rom(&ROM).unwrap();
+    if let Some(key) = held_key {
+        cpu.set_key(key, true);
+    }
+    cpu.run_frame(10).unwrap();
+    assert!(cpu.waiting_for_key());
+    assert_eq!(cpu.program_counter(), 0x202);
+    cpu
+}
+
+#[test]
+fn key_is_accepted_on_release() {
+    let mut cpu = waiting_cpu(None);
+    cpu.set_key(7, true);
+    cpu.run_frame(10).unwrap();
+    assert!(cpu.waiting_for_key());
+    assert_eq!(cpu.program_counter(), 0x202);
+
+    cpu.set_key(7, false);
+    cpu.run_frame(10).unwrap();
+    assert!(!cpu.waiting_for_key());
+    assert_eq!(cpu.registers()[1], 7);
+    assert_eq!(cpu.program_counter(), 0x204);
+}
+
+#[test]
+fn key_held_when_the_wait_starts_is_ignored() {
+    let mut cpu = waiting_cpu(Some(5));
+    cpu.run_frame(10).unwrap();
+    cpu.set_key(5, false);
+    cpu.run_frame(10).unwrap();
+    assert!(cpu.waiting_for_key());
+    assert_eq!(cpu.registers()[1], 0);
+
+    // Pressed again after the wait started it counts
+    cpu.set_key(5, true);
+    cpu.run_frame(10).unwrap();
+    cpu.set_key(5, false);
+    cpu.run_frame(10).unwrap();
+    assert!(!cpu.waiting_for_key());
+    assert_eq!(cpu.registers()[1], 5);
+}
+
+#[test]
+fn only_the_first_pressed_key_counts() {
+    let mut cpu = waiting_cpu(None);
+    cpu.set_key(3, true);
+    cpu.run_frame(10).unwrap();
+    cpu.set_key(9, true);
+    cpu.run_frame(10).unwrap();
+    // Releasing another key doesn't end the wait
+    cpu.set_key(9, false);
+    cpu.run_frame(10).unwrap();
+    assert!(cpu.waiting_for_key());
+    cpu.set_key(3, false);
+    cpu.run_frame(10).unwrap();
+    assert_eq!(cpu.registers()[1], 3);
+}
+
+#[test]
+fn frame_ends_after_one_cycle_while_waiting() {
+    let lines = Lines::default();
+    let mut cpu = Cpu::from_rom(&ROM).unwrap();
+    cpu.set_trace(Some(Trace::new(lines.clone())));
+
+    cpu.run_frame(10).unwrap();
+    assert_eq!(lines.take().len(), 2);
+    for _ in 0..3 {
+        cpu.run_frame(10).unwrap();
+        let executed = lines.take();
+        assert_eq!(executed.len(), 1);
+        assert!(executed[0].contains("202  F10A  LD V1, K"), "{}", executed[0]);
+    }
+    assert_eq!(cpu.registers()[0], 1);
+}
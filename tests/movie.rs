@@ -0,0 +1,68 @@
+use std::fs;
+
+use chip8::{Movie, MovieError, Quirks};
+
+/// 64 bit FNV-1a of the final save state, recorded when the movies were made.
+fn hash(data: &[u8]) -> u64 {
+    data.iter().fold(0xCBF29CE484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001B3)
+    })
+}
+
+fn replay(name: &str) -> u64 {
+    let rom = fs::read(format!("games/{}.ch8", name)).unwrap();
+    let movie = Movie::from_bytes(&fs::read(format!("tests/movies/{}.movie", name)).unwrap()).unwrap();
+    let cpu = movie.play(&rom).unwrap();
+    hash(&cpu.save_state())
+}
+
+#[test]
+fn breakout_replays_to_the_recorded_state() {
+    assert_eq!(replay("breakout"), 0x4E5F4AB42A6E5C07);
+}
+
+#[test]
+fn tetris_replays_to_the_recorded_state() {
+    assert_eq!(replay("tetris"), 0xF3C4CF14C6636A30);
+}
+
+#[test]
+fn movie_with_another_rom_is_rejected() {
+    let movie = Movie::from_bytes(&fs::read("tests/movies/tetris.movie").unwrap()).unwrap();
+    let rom = fs::read("games/breakout.ch8").unwrap();
+    assert!(matches!(movie.play(&rom), Err(MovieError::RomMismatch)));
+}
+
+fn sample_movie() -> Movie {
+    let mut movie = Movie::new(&[0x12, 0x00], Quirks::XO_CHIP, 42, 500);
+    movie.frames = vec![0x0000, 0x0010, 0xFFFF, 0x8001];
+    movie
+}
+
+#[test]
+fn bytes_round_trip() {
+    let movie = sample_movie();
+    assert_eq!(Movie::from_bytes(&movie.to_bytes()).unwrap(), movie);
+}
+
+#[test]
+fn truncated_movie_is_rejected() {
+    let bytes = sample_movie().to_bytes();
+    for len in 0..bytes.len() {
+        assert!(matches!(Movie::from_bytes(&bytes[..len]), Err(MovieError::Truncated)), "length {}", len);
+    }
+}
+
+#[test]
+fn bad_magic_is_rejected() {
+    let mut bytes = sample_movie().to_bytes();
+    bytes[0] = b'X';
+    assert!(matches!(Movie::from_bytes(&bytes), Err(MovieError::InvalidMagic)));
+}
+
+#[test]
+fn unknown_version_is_rejected() {
+    let mut bytes = sample_movie().to_bytes();
+    bytes[4..6].copy_from_slice(&99u16.to_le_bytes());
+    assert!(matches!(Movie::from_bytes(&bytes), Err(MovieError::UnsupportedVersion(99))));
+}
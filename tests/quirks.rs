@@ -0,0 +1,107 @@
+use chip8::{Cpu, LoadError, Quirks};
+
+#[test]
+fn memory_size_is_clamped_to_the_supported_sizes() {
+    let cpu = Cpu::with_quirks(Quirks { memory_size: 0x100, ..Quirks::MODERN });
+    assert_eq!(cpu.memory().len(), 0x1000);
+    assert_eq!(cpu.quirks().memory_size, 0x1000);
+
+    let cpu = Cpu::with_quirks(Quirks { memory_size: 0x20000, ..Quirks::MODERN });
+    assert_eq!(cpu.memory().len(), 0x10000);
+
+    let cpu = Cpu::with_quirks(Quirks { memory_size: 0x2000, ..Quirks::MODERN });
+    assert_eq!(cpu.memory().len(), 0x2000);
+}
+
+#[test]
+fn rom_size_is_limited_by_the_memory_size() {
+    let mut cpu = Cpu::with_quirks(Quirks { memory_size: 0, ..Quirks::MODERN });
+    assert!(cpu.load_rom(&[0; 0xE00]).is_ok());
+    assert!(matches!(cpu.load_rom(&[0; 0xE01]), Err(LoadError::RomTooLarge { size: 0xE01, max_size: 0xE00 })));
+}
+
+/// Loads `rom` and executes `instructions` instructions.
+fn run(quirks: Quirks, rom: &[u8], instructions: usize) -> Cpu {
+    let mut cpu = Cpu::with_quirks(quirks);
+    cpu.load_rom(rom).unwrap();
+    for _ in 0..instructions {
+        cpu.cycle().unwrap();
+    }
+    cpu
+}
+
+#[test]
+fn shifts_use_vy_on_the_vip() {
+    // LD V0, 0x05; LD V1, 0x03; SHR V0, V1; LD V2, 0x05; SHL V2, V1
+    let rom = [0x60, 0x05, 0x61, 0x03, 0x80, 0x16, 0x62, 0x05, 0x82, 0x1E];
+    let cpu = run(Quirks::COSMAC_VIP, &rom, 5);
+    assert_eq!(cpu.registers()[0], 0x01);
+    assert_eq!(cpu.registers()[2], 0x06);
+    let cpu = run(Quirks::MODERN, &rom, 5);
+    assert_eq!(cpu.registers()[0], 0x02);
+    assert_eq!(cpu.registers()[2], 0x0A);
+    assert_eq!(cpu.registers()[0xF], 0);
+}
+
+#[test]
+fn load_and_store_advance_i_by_the_quirk() {
+    // LD I, 0x300; LD [I], V2; LD V2, [I]
+    let rom = [0xA3, 0x00, 0xF2, 0x55, 0xF2, 0x65];
+    for (quirks, after_store, after_load) in [
+        (Quirks::MODERN, 0x300, 0x300),
+        (Quirks::CHIP_48, 0x302, 0x304),
+        (Quirks::COSMAC_VIP, 0x303, 0x306),
+    ] {
+        assert_eq!(run(quirks, &rom, 2).index(), after_store);
+        assert_eq!(run(quirks, &rom, 3).index(), after_load);
+    }
+}
+
+#[test]
+fn jump_with_offset_uses_vx_on_chip_48() {
+    // LD V0, 0x01; LD V2, 0x04; JP V0, 0x210
+    let rom = [0x60, 0x01, 0x62, 0x04, 0xB2, 0x10];
+    assert_eq!(run(Quirks::MODERN, &rom, 3).program_counter(), 0x211);
+    assert_eq!(run(Quirks::CHIP_48, &rom, 3).program_counter(), 0x214);
+}
+
+#[test]
+fn logic_resets_vf_on_the_vip() {
+    for opcode in [0x01, 0x02, 0x03] {
+        // LD VF, 0x05; OR/AND/XOR V0, V1
+        let rom = [0x6F, 0x05, 0x80, 0x10 | opcode];
+        assert_eq!(run(Quirks::COSMAC_VIP, &rom, 2).registers()[0xF], 0);
+        assert_eq!(run(Quirks::MODERN, &rom, 2).registers()[0xF], 5);
+    }
+}
+
+#[test]
+fn sprites_wrap_or_are_clipped_at_the_edges() {
+    // LD V0, 0x3C; LD V1, 0x1F; LD I, 0x20A; DRW V0, V1, 2; row of 8 pixels, twice
+    let rom = [0x60, 0x3C, 0x61, 0x1F, 0xA2, 0x0A, 0xD0, 0x12, 0x00, 0x00, 0xFF, 0xFF];
+    let lit = |cpu: &Cpu, x: usize, y: usize| cpu.video()[y * cpu.width() + x] != 0;
+
+    let cpu = run(Quirks::XO_CHIP, &rom, 4);
+    assert!(lit(&cpu, 63, 31) && lit(&cpu, 0, 31) && lit(&cpu, 3, 0));
+    assert_eq!(cpu.video().iter().filter(|&&pixel| pixel != 0).count(), 16);
+
+    let cpu = run(Quirks::MODERN, &rom, 4);
+    assert!(lit(&cpu, 60, 31) && lit(&cpu, 63, 31));
+    assert!(!lit(&cpu, 0, 31) && !lit(&cpu, 60, 0));
+    assert_eq!(cpu.video().iter().filter(|&&pixel| pixel != 0).count(), 4);
+}
+
+#[test]
+fn draw_waits_for_the_vblank_on_the_vip() {
+    // LD I, 0x050; DRW V0, V0, 1
+    let rom = [0xA0, 0x50, 0xD0, 0x01];
+    let mut cpu = run(Quirks::COSMAC_VIP, &rom, 3);
+    assert_eq!(cpu.program_counter(), 0x202);
+    assert!(cpu.video().iter().all(|&pixel| pixel == 0));
+    cpu.tick_timers();
+    cpu.cycle().unwrap();
+    assert_eq!(cpu.program_counter(), 0x204);
+    assert!(cpu.video().iter().any(|&pixel| pixel != 0));
+
+    assert_eq!(run(Quirks::MODERN, &rom, 2).program_counter(), 0x204);
+}
@@ -0,0 +1,74 @@
+use chip8::{Cpu, Rewind};
+
+/// Counts V0 up every instruction and switches to the 128x64 mode once it reaches 0x10.
+/// ADD V0, 0x01; SE V0, 0x10; JP 0x200; HIGH; JP 0x200
+const ROM: [u8; 10] = [0x70, 0x01, 0x30, 0x10, 0x12, 0x00, 0x00, 0xFF, 0x12, 0x00];
+
+fn cpu() -> Cpu {
+    let mut cpu = Cpu::from_rom(&ROM).unwrap();
+    cpu.set_seed(1);
+    cpu
+}
+
+/// Runs `frames` frames, pushing every frame to `rewind` and returning the pushed states.
+fn record(cpu: &mut Cpu, rewind: &mut Rewind, frames: usize) -> Vec<Vec<u8>> {
+    let mut states = Vec::new();
+    for _ in 0..frames {
+        cpu.run_frame(1).unwrap();
+        rewind.push(cpu);
+        states.push(cpu.save_state());
+    }
+    states
+}
+
+#[test]
+fn rewinding_restores_every_earlier_frame() {
+    let mut cpu = cpu();
+    let mut rewind = Rewind::new(2);
+    let states = record(&mut cpu, &mut rewind, 80);
+    assert!(cpu.hires());
+    // The resolution switch changes the size of the state
+    assert!(states.first().unwrap().len() != states.last().unwrap().len());
+
+    assert_eq!(rewind.len(), 79);
+    for state in states.iter().rev().skip(1) {
+        assert!(rewind.rewind(&mut cpu));
+        assert!(cpu.save_state() == *state);
+    }
+    assert!(!cpu.hires());
+    assert!(rewind.is_empty());
+    assert!(!rewind.rewind(&mut cpu));
+}
+
+#[test]
+fn history_is_limited_to_the_capacity() {
+    let mut cpu = cpu();
+    let mut rewind = Rewind::new(1);
+    let states = record(&mut cpu, &mut rewind, 100);
+    assert_eq!(rewind.len(), 60);
+    while rewind.rewind(&mut cpu) {}
+    assert!(cpu.save_state() == states[100 - 61]);
+}
+
+#[test]
+fn frames_pushed_after_an_exhausted_rewind_can_be_rewound() {
+    let mut cpu = cpu();
+    let mut rewind = Rewind::new(1);
+    let states = record(&mut cpu, &mut rewind, 3);
+    while rewind.rewind(&mut cpu) {}
+    assert!(cpu.save_state() == states[0]);
+    assert!(!rewind.rewind(&mut cpu));
+
+    record(&mut cpu, &mut rewind, 1);
+    assert!(rewind.rewind(&mut cpu));
+    assert!(cpu.save_state() == states[0]);
+}
+
+#[test]
+fn zero_seconds_disables_rewinding() {
+    let mut cpu = cpu();
+    let mut rewind = Rewind::new(0);
+    record(&mut cpu, &mut rewind, 10);
+    assert!(rewind.is_empty());
+    assert!(!rewind.rewind(&mut cpu));
+}
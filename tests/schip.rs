@@ -0,0 +1,110 @@
+use chip8::{Cpu, Quirks, HIRES_VIDEO_HEIGHT, HIRES_VIDEO_WIDTH, VIDEO_HEIGHT, VIDEO_WIDTH};
+
+/// Loads `rom` and executes `instructions` instructions.
+fn run(rom: &[u8], instructions: usize) -> Cpu {
+    let mut cpu = Cpu::with_quirks(Quirks::SUPER_CHIP);
+    cpu.load_rom(rom).unwrap();
+    for _ in 0..instructions {
+        cpu.cycle().unwrap();
+    }
+    cpu
+}
+
+fn lit_pixels(cpu: &Cpu) -> Vec<(usize, usize)> {
+    (0..cpu.video().len())
+        .filter(|&i| cpu.video()[i] != 0)
+        .map(|i| (i % cpu.width(), i / cpu.width()))
+        .collect()
+}
+
+#[test]
+fn resolution_switches_resize_and_clear_the_display() {
+    // LD I, 0x050; DRW V0, V0, 5; HIGH; DRW V0, V0, 5; LOW
+    let rom = [0xA0, 0x50, 0xD0, 0x05, 0x00, 0xFF, 0xD0, 0x05, 0x00, 0xFE];
+    let cpu = run(&rom, 2);
+    assert!(!cpu.hires());
+    assert_eq!((cpu.width(), cpu.height()), (VIDEO_WIDTH, VIDEO_HEIGHT));
+
+    let cpu = run(&rom, 3);
+    assert!(cpu.hires());
+    assert_eq!((cpu.width(), cpu.height()), (HIRES_VIDEO_WIDTH, HIRES_VIDEO_HEIGHT));
+    assert_eq!(cpu.video().len(), HIRES_VIDEO_WIDTH * HIRES_VIDEO_HEIGHT);
+    assert!(lit_pixels(&cpu).is_empty());
+
+    let cpu = run(&rom, 4);
+    assert!(!lit_pixels(&cpu).is_empty());
+    let cpu = run(&rom, 5);
+    assert!(!cpu.hires());
+    assert_eq!(cpu.video().len(), VIDEO_WIDTH * VIDEO_HEIGHT);
+    assert!(lit_pixels(&cpu).is_empty());
+}
+
+#[test]
+fn dxy0_draws_a_16x16_sprite() {
+    // HIGH; LD I, 0x20A; DRW V0, V0, 0; DRW V0, V0, 0; 32 bytes of 0xFF at 0x20A
+    let mut rom = vec![0x00, 0xFF, 0xA2, 0x0A, 0xD0, 0x00, 0xD0, 0x00, 0x00, 0x00];
+    rom.extend([0xFF; 32]);
+    let cpu = run(&rom, 3);
+    let pixels = lit_pixels(&cpu);
+    assert_eq!(pixels.len(), 256);
+    assert!(pixels.iter().all(|&(x, y)| x < 16 && y < 16));
+    assert_eq!(cpu.registers()[0xF], 0);
+
+    let cpu = run(&rom, 4);
+    assert!(lit_pixels(&cpu).is_empty());
+    assert_eq!(cpu.registers()[0xF], 1);
+}
+
+#[test]
+fn scrolling_moves_the_display() {
+    // HIGH; LD V0, 0x0A; LD V1, 0x05; LD I, 0x050; DRW V0, V1, 1 (4 pixels from (10, 5)); SCD 2; SCR; SCL; SCL
+    let rom = [
+        0x00, 0xFF, 0x60, 0x0A, 0x61, 0x05, 0xA0, 0x50, 0xD0, 0x11, 0x00, 0xC2, 0x00, 0xFB, 0x00, 0xFC, 0x00, 0xFC,
+    ];
+    let row = |x: usize, y: usize| vec![(x, y), (x + 1, y), (x + 2, y), (x + 3, y)];
+    assert_eq!(lit_pixels(&run(&rom, 5)), row(10, 5));
+    assert_eq!(lit_pixels(&run(&rom, 6)), row(10, 7));
+    assert_eq!(lit_pixels(&run(&rom, 7)), row(14, 7));
+    assert_eq!(lit_pixels(&run(&rom, 8)), row(10, 7));
+    assert_eq!(lit_pixels(&run(&rom, 9)), row(6, 7));
+}
+
+#[test]
+fn scrolling_drops_pixels_at_the_edge() {
+    // LD V0, 0x3C; LD I, 0x050; DRW V0, V1, 1 (4 pixels from (60, 0)); SCR
+    let cpu = run(&[0x60, 0x3C, 0xA0, 0x50, 0xD0, 0x11, 0x00, 0xFB], 4);
+    assert!(lit_pixels(&cpu).is_empty());
+}
+
+#[test]
+fn big_font_is_selected_with_fx30() {
+    // LD V0, 0x03; LD F, V0; LD HF, V0
+    let rom = [0x60, 0x03, 0xF0, 0x29, 0xF0, 0x30];
+    let small = run(&rom, 2).index() as usize;
+    let cpu = run(&rom, 3);
+    let big = cpu.index() as usize;
+    assert_eq!(small, 0x50 + 3 * 5);
+    // The 10 byte digits follow the 16 small digits
+    assert_eq!(big, 0x50 + 16 * 5 + 3 * 10);
+    assert!(cpu.memory()[big..big + 10].iter().all(|&row| row != 0));
+}
+
+#[test]
+fn flags_are_saved_and_restored_with_fx75_and_fx85() {
+    // LD V0, 0x01; LD V1, 0x02; LD V2, 0x03; LD R, V1; LD V0, 0x00; LD V1, 0x00; LD V2, 0x00; LD V2, R
+    let rom = [
+        0x60, 0x01, 0x61, 0x02, 0x62, 0x03, 0xF1, 0x75, 0x60, 0x00, 0x61, 0x00, 0x62, 0x00, 0xF2, 0x85,
+    ];
+    let cpu = run(&rom, 8);
+    // Only V0-V1 were saved, the third flag is still 0
+    assert_eq!(cpu.registers()[..3], [1, 2, 0]);
+}
+
+#[test]
+fn exit_stops_on_the_instruction() {
+    let mut cpu = run(&[0x00, 0xFD], 1);
+    assert!(cpu.exited());
+    assert_eq!(cpu.program_counter(), 0x200);
+    cpu.cycle().unwrap();
+    assert_eq!(cpu.program_counter(), 0x200);
+}
@@ -0,0 +1,59 @@
+use std::fs;
+
+use chip8::{Cpu, Quirks, StateError};
+
+fn breakout() -> Cpu {
+    let mut cpu = Cpu::from_rom(&fs::read("games/breakout.ch8").unwrap()).unwrap();
+    cpu.set_seed(7);
+    cpu
+}
+
+fn run(cpu: &mut Cpu, frames: usize) {
+    for frame in 0..frames {
+        // Move the paddle now and then so the game doesn't just idle
+        cpu.set_key(if frame % 40 < 20 { 4 } else { 6 }, frame % 3 == 0);
+        cpu.run_frame(10).unwrap();
+    }
+}
+
+#[test]
+fn loaded_state_continues_like_the_original() {
+    let mut cpu = breakout();
+    run(&mut cpu, 120);
+    let state = cpu.save_state();
+    run(&mut cpu, 300);
+
+    // The random number generator is part of the state, so a different seed doesn't matter
+    let mut restored = Cpu::from_rom(&[0x00, 0xE0]).unwrap();
+    restored.set_seed(99);
+    restored.load_state(&state).unwrap();
+    assert!(restored.save_state() == state);
+    run(&mut restored, 300);
+    assert!(restored.save_state() == cpu.save_state());
+}
+
+#[test]
+fn truncated_state_is_rejected() {
+    let state = breakout().save_state();
+    let mut cpu = breakout();
+    run(&mut cpu, 10);
+    let before = cpu.save_state();
+    for len in [0, 3, 6, 40, state.len() / 2, state.len() - 1] {
+        assert!(matches!(cpu.load_state(&state[..len]), Err(StateError::Truncated)), "length {}", len);
+    }
+    assert!(cpu.save_state() == before);
+}
+
+#[test]
+fn bad_magic_is_rejected() {
+    let mut state = breakout().save_state();
+    state[0] = b'X';
+    assert!(matches!(breakout().load_state(&state), Err(StateError::InvalidMagic)));
+}
+
+#[test]
+fn state_with_another_memory_size_is_rejected() {
+    let state = Cpu::with_quirks(Quirks::XO_CHIP).save_state();
+    let result = breakout().load_state(&state);
+    assert!(matches!(result, Err(StateError::MemorySizeMismatch { expected: 4096, found: 65536 })));
+}
@@ -0,0 +1,89 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use chip8::{Cpu, Trace};
+
+/// LD V0, 0x01; LD V1, 0x02; LD V2, 0x03; RET with an empty stack
+const ROM: [u8; 8] = [0x60, 0x01, 0x61, 0x02, 0x62, 0x03, 0x00, 0xEE];
+
+/// Collects the trace in memory.
+#[derive(Clone, Default)]
+struct Lines(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Lines {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Traces `rom` until `instructions` instructions ran or one fails, returning the trace lines.
+fn trace(rom: &[u8], instructions: usize, configure: impl FnOnce(&mut Trace)) -> Vec<String> {
+    let lines = Lines::default();
+    let mut trace = Trace::new(lines.clone());
+    configure(&mut trace);
+
+    let mut cpu = Cpu::from_rom(rom).unwrap();
+    cpu.set_trace(Some(trace));
+    for _ in 0..instructions {
+        if cpu.cycle().is_err() {
+            break;
+        }
+    }
+    cpu.take_trace().unwrap().finish().unwrap();
+
+    let text = String::from_utf8(lines.0.take()).unwrap();
+    text.lines().map(str::to_string).collect()
+}
+
+#[test]
+fn lines_show_the_instruction_and_changed_registers() {
+    // LD V0, 0x12; LD V1, 0x08; LD I, 0x206; LD [I], V1 overwriting itself with JP 0x208; EXIT
+    let rom = [0x60, 0x12, 0x61, 0x08, 0xA2, 0x06, 0xF1, 0x55, 0x00, 0xFD];
+    assert_eq!(
+        trace(&rom, 5, |_| ()),
+        [
+            "         1  200  6012  LD V0, 0x12           V0=12",
+            "         2  202  6108  LD V1, 0x08           V1=08",
+            "         3  204  A206  LD I, 0x206           I=206",
+            // Traced as the instruction that ran, not the jump it was replaced with
+            "         4  206  F155  LD [I], V1",
+            "         5  208  00FD  EXIT",
+        ]
+    );
+}
+
+#[test]
+fn only_instructions_within_the_address_range_are_written() {
+    let lines = trace(&ROM[..6], 3, |trace| trace.set_address_range(0x202..=0x203));
+    // Cycles are still counted outside of the range
+    assert_eq!(lines, ["         2  202  6102  LD V1, 0x02           V1=02"]);
+}
+
+#[test]
+fn errors_are_written_after_the_instruction_lines() {
+    let lines = trace(&ROM, 10, |_| ());
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[3], "         4  stack underflow at 0x206 (opcode 0x00EE)");
+}
+
+#[test]
+fn ring_buffer_is_dumped_on_an_error() {
+    // Without an error nothing is written
+    assert!(trace(&ROM, 3, |trace| trace.set_ring_buffer(2)).is_empty());
+
+    assert_eq!(
+        trace(&ROM, 10, |trace| trace.set_ring_buffer(2)),
+        [
+            "Last 2 instructions before the error:",
+            "         2  202  6102  LD V1, 0x02           V1=02",
+            "         3  204  6203  LD V2, 0x03           V2=03",
+            "         4  stack underflow at 0x206 (opcode 0x00EE)",
+        ]
+    );
+}
+
@@ -0,0 +1,111 @@
+use chip8::{Cpu, Quirks};
+
+/// Loads `rom` and executes `instructions` instructions.
+fn run(rom: &[u8], instructions: usize) -> Cpu {
+    let mut cpu = Cpu::with_quirks(Quirks::XO_CHIP);
+    cpu.load_rom(rom).unwrap();
+    for _ in 0..instructions {
+        cpu.cycle().unwrap();
+    }
+    cpu
+}
+
+/// LD V1, 0x01; LD V2, 0x02; LD V3, 0x03; LD I, 0x300 followed by `code`.
+fn with_registers(code: &[u8]) -> Vec<u8> {
+    let mut rom = vec![0x61, 0x01, 0x62, 0x02, 0x63, 0x03, 0xA3, 0x00];
+    rom.extend_from_slice(code);
+    rom
+}
+
+#[test]
+fn register_ranges_are_saved_in_both_directions() {
+    // SAVE V1, V3
+    let cpu = run(&with_registers(&[0x51, 0x32]), 5);
+    assert_eq!(cpu.memory()[0x300..0x304], [1, 2, 3, 0]);
+    assert_eq!(cpu.index(), 0x300);
+    // SAVE V3, V1
+    let cpu = run(&with_registers(&[0x53, 0x12]), 5);
+    assert_eq!(cpu.memory()[0x300..0x304], [3, 2, 1, 0]);
+}
+
+#[test]
+fn register_ranges_are_loaded_in_both_directions() {
+    // LOAD V1, V3 and LOAD V3, V1 of 7, 8, 9 at 0x300
+    for (opcode, expected) in [([0x51, 0x33], [7, 8, 9]), ([0x53, 0x13], [9, 8, 7])] {
+        let mut cpu = run(&with_registers(&opcode), 4);
+        cpu.memory_mut()[0x300..0x303].copy_from_slice(&[7, 8, 9]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.registers()[1..4], expected);
+        assert_eq!(cpu.index(), 0x300);
+    }
+}
+
+#[test]
+fn long_load_reaches_all_of_memory() {
+    // LD I, LONG 0xFFF0; LD V0, 0x2A; LD [I], V0
+    let rom = [0xF0, 0x00, 0xFF, 0xF0, 0x60, 0x2A, 0xF0, 0x55];
+    let cpu = run(&rom, 1);
+    assert_eq!(cpu.program_counter(), 0x204);
+    assert_eq!(cpu.index(), 0xFFF0);
+    let cpu = run(&rom, 3);
+    assert_eq!(cpu.memory().len(), 0x10000);
+    assert_eq!(cpu.memory()[0xFFF0], 0x2A);
+}
+
+#[test]
+fn skips_step_over_the_whole_long_load() {
+    // SE V0, 0x00; LD I, LONG 0x1234; SNE V0, 0x00; LD I, LONG 0x1234
+    let rom = [0x30, 0x00, 0xF0, 0x00, 0x12, 0x34, 0x40, 0x00, 0xF0, 0x00, 0x12, 0x34];
+    let cpu = run(&rom, 1);
+    assert_eq!(cpu.program_counter(), 0x206);
+    let cpu = run(&rom, 3);
+    assert_eq!(cpu.program_counter(), 0x20C);
+    assert_eq!(cpu.index(), 0x1234);
+}
+
+#[test]
+fn drawing_uses_the_selected_planes() {
+    // LD I, 0x050; PLANE 2; DRW V0, V0, 1; PLANE 3; LD I, 0x210; DRW V0, V0, 1; sprites 0x80 and 0xC0 at 0x210
+    let rom = [
+        0xA0, 0x50, 0xF2, 0x01, 0xD0, 0x01, 0xF3, 0x01, 0xA2, 0x10, 0xD0, 0x01, 0x00, 0x00, 0x00, 0x00, 0x80, 0xC0,
+    ];
+    let cpu = run(&rom, 3);
+    assert_eq!(cpu.video()[..5], [2, 2, 2, 2, 0]);
+
+    // The first plane gets 0x80, the second plane 0xC0 which erases two of its pixels
+    let cpu = run(&rom, 6);
+    assert_eq!(cpu.video()[..5], [1, 0, 2, 2, 0]);
+    assert_eq!(cpu.registers()[0xF], 1);
+}
+
+#[test]
+fn clear_and_scroll_only_affect_the_selected_planes() {
+    // PLANE 3; LD I, 0x050; DRW V0, V0, 1 into both planes; PLANE 1; SCD 1; CLS
+    let rom = [0xF3, 0x01, 0xA0, 0x50, 0xD0, 0x01, 0xF1, 0x01, 0x00, 0xC1, 0x00, 0xE0];
+    let cpu = run(&rom, 3);
+    // The second plane draws the next sprite row, 0x90
+    assert_eq!(cpu.video()[..4], [3, 1, 1, 3]);
+
+    let cpu = run(&rom, 5);
+    let width = cpu.width();
+    assert_eq!(cpu.video()[..4], [2, 0, 0, 2]);
+    assert_eq!(cpu.video()[width..width + 4], [1, 1, 1, 1]);
+
+    let cpu = run(&rom, 6);
+    assert_eq!(cpu.video()[..4], [2, 0, 0, 2]);
+    assert!(cpu.video()[width..].iter().all(|&pixel| pixel == 0));
+}
+
+#[test]
+fn audio_pattern_and_pitch_are_loaded() {
+    // LD I, 0x208; AUDIO; LD V0, 0x70; PITCH V0; 16 pattern bytes at 0x208
+    let mut rom = vec![0xA2, 0x08, 0xF0, 0x02, 0x60, 0x70, 0xF0, 0x3A];
+    rom.extend(0..16u8);
+    let cpu = run(&rom, 2);
+    assert_eq!(cpu.audio_pattern(), Some(&std::array::from_fn(|i| i as u8)));
+    assert_eq!(cpu.audio_pattern_rate(), 4000.0);
+
+    // 48 steps above the default pitch of 64 double the rate
+    let cpu = run(&rom, 4);
+    assert_eq!(cpu.audio_pattern_rate(), 8000.0);
+}